@@ -3,6 +3,7 @@ use std::{collections::HashMap, str::FromStr, u64, vec};
 use anyhow::{Ok, Result};
 use aptos_sdk::{
     bcs,
+    crypto::ed25519::Ed25519Signature,
     move_types::{
         account_address::AccountAddress,
         identifier::Identifier,
@@ -16,7 +17,7 @@ use aptos_sdk::{
     types::{
         LocalAccount,
         chain_id::{ChainId, NamedChain},
-        transaction::{EntryFunction, TransactionPayload},
+        transaction::{EntryFunction, RawTransaction, SignedTransaction, TransactionPayload},
     },
 };
 use serde::Deserialize;
@@ -265,7 +266,37 @@ impl Client {
         token: &String,
         amount: u64,
         repay_only: bool,
+        opts: Option<TxOptions>,
     ) -> Result<TransactionInfo> {
+        let efn = self
+            .deposit_payload(profile, token, amount, repay_only)
+            .await?;
+
+        self.send_tx(efn, opts).await
+    }
+
+    pub async fn simulate_deposit(
+        &self,
+        profile: &Profile,
+        token: &String,
+        amount: u64,
+        repay_only: bool,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self
+            .deposit_payload(profile, token, amount, repay_only)
+            .await?;
+
+        self.simulate_tx(efn, opts).await
+    }
+
+    async fn deposit_payload(
+        &self,
+        profile: &Profile,
+        token: &String,
+        amount: u64,
+        repay_only: bool,
+    ) -> Result<EntryFunction> {
         let is_wrapped_token = self.is_wrapped_coin(token).await?;
         let mut args = vec![bcs::to_bytes(&profile.name)?, bcs::to_bytes(&amount)?];
         let method = if is_wrapped_token {
@@ -286,32 +317,46 @@ impl Client {
             args,
         );
 
-        let sequence_number = self.get_sequence_number(self.wallet.address()).await?;
+        Ok(efn)
+    }
 
-        let tx = TransactionFactory::new(self.chain_id)
-            .payload(TransactionPayload::EntryFunction(efn))
-            .sequence_number(sequence_number)
-            .sender(self.wallet.address())
-            .max_gas_amount(MAX_GAS_AMOUNT)
-            .build();
+    pub async fn withdraw(
+        &self,
+        profile: &Profile,
+        token: &String,
+        amount: u64,
+        allow_borrow: bool,
+        opts: Option<TxOptions>,
+    ) -> Result<TransactionInfo> {
+        let efn = self
+            .withdraw_payload(profile, token, amount, allow_borrow)
+            .await?;
 
-        let signed_txn = tx.sign(&self.wallet.private_key(), self.wallet.public_key().clone())?;
-        let pending_txn = self.rest_client.submit(&signed_txn).await?;
-        let tx = self
-            .rest_client
-            .wait_for_transaction(&pending_txn.inner())
+        self.send_tx(efn, opts).await
+    }
+
+    pub async fn simulate_withdraw(
+        &self,
+        profile: &Profile,
+        token: &String,
+        amount: u64,
+        allow_borrow: bool,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self
+            .withdraw_payload(profile, token, amount, allow_borrow)
             .await?;
 
-        Ok(tx.inner().transaction_info().unwrap().clone())
+        self.simulate_tx(efn, opts).await
     }
 
-    pub async fn withdraw(
+    async fn withdraw_payload(
         &self,
         profile: &Profile,
         token: &String,
         amount: u64,
         allow_borrow: bool,
-    ) -> Result<TransactionInfo> {
+    ) -> Result<EntryFunction> {
         let is_wrapped_token = self.is_wrapped_coin(token).await?;
         let mut args = vec![bcs::to_bytes(&profile.name)?, bcs::to_bytes(&amount)?];
         let method = if is_wrapped_token {
@@ -337,23 +382,7 @@ impl Client {
             ],
         );
 
-        let sequence_number = self.get_sequence_number(self.wallet.address()).await?;
-
-        let tx = TransactionFactory::new(self.chain_id)
-            .payload(TransactionPayload::EntryFunction(efn))
-            .sequence_number(sequence_number)
-            .sender(self.wallet.address())
-            .max_gas_amount(MAX_GAS_AMOUNT)
-            .build();
-
-        let signed_txn = tx.sign(&self.wallet.private_key(), self.wallet.public_key().clone())?;
-        let pending_txn = self.rest_client.submit(&signed_txn).await?;
-        let tx = self
-            .rest_client
-            .wait_for_transaction(&pending_txn.inner())
-            .await?;
-
-        Ok(tx.inner().transaction_info().unwrap().clone())
+        Ok(efn)
     }
 
     pub async fn get_deposited_amount(
@@ -439,6 +468,114 @@ impl Client {
         Ok(n)
     }
 
+    fn build_tx(
+        &self,
+        efn: EntryFunction,
+        sequence_number: u64,
+        opts: &TxOptions,
+    ) -> RawTransaction {
+        let builder = TransactionFactory::new(self.chain_id)
+            .payload(TransactionPayload::EntryFunction(efn))
+            .sequence_number(sequence_number)
+            .sender(self.wallet.address())
+            .max_gas_amount(MAX_GAS_AMOUNT);
+
+        opts.apply(builder).build()
+    }
+
+    /// Turns the gas policy of `opts` into a fixed gas unit price and max gas amount
+    async fn resolve_tx_options(
+        &self,
+        efn: &EntryFunction,
+        opts: Option<TxOptions>,
+    ) -> Result<TxOptions> {
+        let mut opts = opts.unwrap_or_default();
+        opts.validate()?;
+
+        if opts.needs_gas_estimate() {
+            opts.fix_gas_unit_price(self.estimate_gas_price().await?);
+        }
+
+        if let Some(margin) = opts.simulate_gas_margin.take() {
+            let simulation = self.simulate_resolved(efn.clone(), &opts).await?;
+            if !simulation.success {
+                return Err(anyhow::anyhow!("Simulation failed: {}", simulation.vm_status));
+            }
+            opts.max_gas_amount = Some(TxOptions::max_gas_with_margin(simulation.gas_used, margin));
+        }
+
+        Ok(opts)
+    }
+
+    async fn estimate_gas_price(&self) -> Result<u64> {
+        let estimation = self.rest_client.estimate_gas_price().await?.into_inner();
+
+        Ok(estimation.gas_estimate)
+    }
+
+    async fn send_tx(
+        &self,
+        efn: EntryFunction,
+        opts: Option<TxOptions>,
+    ) -> Result<TransactionInfo> {
+        let opts = self.resolve_tx_options(&efn, opts).await?;
+        let sequence_number = self.get_sequence_number(self.wallet.address()).await?;
+        let tx = self.build_tx(efn, sequence_number, &opts);
+
+        let signed_txn = tx.sign(&self.wallet.private_key(), self.wallet.public_key().clone())?;
+        let pending_txn = self.rest_client.submit(&signed_txn).await?;
+        let tx = self
+            .rest_client
+            .wait_for_transaction(&pending_txn.inner())
+            .await?;
+
+        Ok(tx.inner().transaction_info()?.clone())
+    }
+
+    async fn simulate_tx(
+        &self,
+        efn: EntryFunction,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let mut opts = opts.unwrap_or_default();
+        opts.simulate_gas_margin = None;
+        let opts = self.resolve_tx_options(&efn, Some(opts)).await?;
+
+        self.simulate_resolved(efn, &opts).await
+    }
+
+    async fn simulate_resolved(
+        &self,
+        efn: EntryFunction,
+        opts: &TxOptions,
+    ) -> Result<SimulationResult> {
+        let sequence_number = self.get_sequence_number(self.wallet.address()).await?;
+        let tx = self.build_tx(efn, sequence_number, opts);
+
+        // simulation requires an invalid signature
+        let signed_txn = SignedTransaction::new(
+            tx,
+            self.wallet.public_key().clone(),
+            Ed25519Signature::dummy_signature(),
+        );
+        let txn = self
+            .rest_client
+            .simulate(&signed_txn)
+            .await?
+            .into_inner()
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("empty simulation response"))?;
+
+        Ok(SimulationResult {
+            success: txn.info.success,
+            vm_status: txn.info.vm_status,
+            gas_used: txn.info.gas_used.0,
+            gas_unit_price: txn.request.gas_unit_price.0,
+            events: txn.events,
+        })
+    }
+
     async fn query_indexer<T>(&self, query: String) -> Result<T>
     where
         T: for<'de> Deserialize<'de>,
@@ -739,8 +876,19 @@ impl Client {
         profile: &Profile,
         token: &String,
         amount: u64,
+        opts: Option<TxOptions>,
     ) -> Result<TransactionInfo> {
-        self.withdraw(profile, token, amount, true).await
+        self.withdraw(profile, token, amount, true, opts).await
+    }
+
+    pub async fn simulate_borrow(
+        &self,
+        profile: &Profile,
+        token: &String,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        self.simulate_withdraw(profile, token, amount, true, opts).await
     }
 
     pub async fn swap(
@@ -748,7 +896,32 @@ impl Client {
         from: &String,
         to: &String,
         from_amount: u64,
+        opts: Option<TxOptions>,
     ) -> Result<TransactionInfo> {
+        let efn = self.swap_payload(from, to, from_amount).await?;
+
+        self.send_tx(efn, opts).await
+    }
+
+    pub async fn simulate_swap(
+        &self,
+        from: &String,
+        to: &String,
+        from_amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.swap_payload(from, to, from_amount).await?;
+
+        self.simulate_tx(efn, opts).await
+    }
+
+    /// Builds the swap entry function from the best Panora route
+    async fn swap_payload(
+        &self,
+        from: &String,
+        to: &String,
+        from_amount: u64,
+    ) -> Result<EntryFunction> {
         let mut u = Url::parse("https://api.panora.exchange/swap")?;
         u.query_pairs_mut()
             .append_pair("chainId", "1")
@@ -883,26 +1056,11 @@ impl Client {
             ],
         );
 
-        let sequence_number = self.get_sequence_number(self.wallet.address()).await?;
-        let tx = TransactionFactory::new(self.chain_id)
-            .payload(TransactionPayload::EntryFunction(entry_function))
-            .sequence_number(sequence_number)
-            .sender(self.wallet.address())
-            .max_gas_amount(MAX_GAS_AMOUNT)
-            .build();
-
-        // dbg!(&tx);
-        let signed_txn = tx.sign(&self.wallet.private_key(), self.wallet.public_key().clone())?;
-        let pending_txn = self.rest_client.submit(&signed_txn).await?;
-        let tx = self
-            .rest_client
-            .wait_for_transaction(&pending_txn.inner())
-            .await?;
-
-        Ok(tx.inner().transaction_info().unwrap().clone())
+        Ok(entry_function)
     }
 
-    async fn get_rewards(&self, token: &String, farm_type: FarmType) -> Result<Vec<Reward>> {
+    /// Returns every reward token of the farm of `token`
+    pub async fn get_rewards(&self, token: &String, farm_type: FarmType) -> Result<Vec<Reward>> {
         let resp = self
             .rest_client
             .view(
@@ -933,58 +1091,76 @@ impl Client {
                 .clone(),
         )?;
 
-        let rewards: Vec<Reward> = items
-            .into_iter()
-            .filter_map(|item| {
-                if let (Some(reward), Some(reward_type)) =
-                    (item.rewards.into_iter().next(), item.reward_types.get(0))
-                {
-                    let mut reward = reward;
-                    reward.token_address = reward_type.decode();
-                    reward.farm_type = farm_type;
-                    reward.total_shares = item.share.parse().unwrap_or_default();
-                    Some(reward)
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let mut rewards = vec![];
+        for item in items {
+            rewards.extend(item.into_rewards(farm_type)?);
+        }
 
         Ok(rewards)
     }
 
-    pub async fn get_borrow_reward_apr(&self, reserve: &ReserveDetail) -> Result<f64> {
-        let reserve_price = self.get_price(&reserve.token_address).await?;
-        let rewards = self
-            .get_rewards(&reserve.token_address, FarmType::BorrowFarming)
-            .await?;
-        if rewards.is_empty() {
-            return Ok(0.0);
-        }
+    /// Returns the APR of every reward token of the borrow farm of the reserve
+    pub async fn get_borrow_reward_aprs(&self, reserve: &ReserveDetail) -> Result<Vec<RewardApr>> {
+        self.get_reward_aprs(reserve, FarmType::BorrowFarming).await
+    }
 
-        let rw = rewards.get(0).unwrap(); // TODO: handle multi rewards
-        let rw_price = self.get_price(&rw.token_address).await?;
+    /// Returns the APR of every reward token of the deposit farm of the reserve
+    pub async fn get_deposit_reward_aprs(&self, reserve: &ReserveDetail) -> Result<Vec<RewardApr>> {
+        self.get_reward_aprs(reserve, FarmType::DepositFarming).await
+    }
 
-        let apr = reserve.get_reward_apr(rw, reserve_price, rw_price);
+    /// Sum of the APRs of every borrow reward token with a price
+    pub async fn get_borrow_reward_apr(&self, reserve: &ReserveDetail) -> Result<f64> {
+        let aprs = self.get_borrow_reward_aprs(reserve).await?;
 
-        Ok(apr)
+        Ok(aprs.iter().filter_map(|reward| reward.apr).sum())
     }
 
+    /// Sum of the APRs of every deposit reward token with a price
     pub async fn get_deposit_reward_apr(&self, reserve: &ReserveDetail) -> Result<f64> {
-        let reserve_price = self.get_price(&reserve.token_address).await?;
-        let rewards = self
-            .get_rewards(&reserve.token_address, FarmType::DepositFarming)
-            .await?;
+        let aprs = self.get_deposit_reward_aprs(reserve).await?;
+
+        Ok(aprs.iter().filter_map(|reward| reward.apr).sum())
+    }
+
+    async fn get_reward_aprs(
+        &self,
+        reserve: &ReserveDetail,
+        farm_type: FarmType,
+    ) -> Result<Vec<RewardApr>> {
+        let rewards = self.get_rewards(&reserve.token_address, farm_type).await?;
         if rewards.is_empty() {
-            return Ok(0.0);
+            return Ok(vec![]);
         }
 
-        let rw = rewards.get(0).unwrap(); // TODO: handle multi rewards
-        let rw_price = self.get_price(&rw.token_address).await?;
+        let reserve_price = self.get_price(&reserve.token_address).await?;
+        let mut aprs = vec![];
+        for reward in rewards {
+            let reward_price = self.get_reward_price(&reward.token_address).await?;
+            aprs.push(RewardApr {
+                apr: reward_price
+                    .map(|price| reserve.get_reward_apr(&reward, reserve_price, price)),
+                daily_emission: reward.daily_emission(),
+                remaining: reward.remaining(),
+                token: reward.token_address,
+            });
+        }
 
-        let apr = reserve.get_reward_apr(rw, reserve_price, rw_price);
+        Ok(aprs)
+    }
 
-        Ok(apr)
+    /// Reward tokens are not necessarily listed in the oracle, `None` when it has no price
+    async fn get_reward_price(&self, token_address: &String) -> Result<Option<u64>> {
+        self.get_price(token_address)
+            .await
+            .map(Some)
+            .or_else(|err| {
+                if is_price_not_found(&err.to_string()) {
+                    Ok(None)
+                } else {
+                    Err(err)
+                }
+            })
     }
 
     pub async fn get_wrapped_fa(&self, coin_token: &String) -> Result<String> {
@@ -1022,6 +1198,11 @@ impl Client {
     }
 }
 
+/// `oracle::get_reserve_price` aborts for a token without a price feed
+fn is_price_not_found(message: &str) -> bool {
+    message.contains("Move abort") && message.contains("::oracle")
+}
+
 #[derive(Debug, Deserialize)]
 struct RewardItem {
     reward_types: Vec<TableKey>,
@@ -1029,3 +1210,119 @@ struct RewardItem {
     share: String,
     timestamp: String,
 }
+
+impl RewardItem {
+    /// `rewards` and `reward_types` are parallel vectors, one entry per reward token
+    fn into_rewards(self, farm_type: FarmType) -> Result<Vec<Reward>> {
+        if self.rewards.len() != self.reward_types.len() {
+            return Err(anyhow::anyhow!(
+                "{} rewards for {} reward types",
+                self.rewards.len(),
+                self.reward_types.len()
+            ));
+        }
+        let total_shares = self
+            .share
+            .parse()
+            .map_err(|e| anyhow::anyhow!("invalid share {}: {}", self.share, e))?;
+
+        let rewards = self
+            .rewards
+            .into_iter()
+            .zip(self.reward_types.iter())
+            .map(|(mut reward, reward_type)| {
+                reward.token_address = reward_type.decode();
+                reward.farm_type = farm_type;
+                reward.total_shares = total_shares;
+                reward
+            })
+            .collect();
+
+        Ok(rewards)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_rewards() {
+        let json: Value = serde_json::from_str(
+            r#"{
+                "reward_types": [
+                    {
+                        "account_address": "0x1",
+                        "module_name": "0x6170746f735f636f696e",
+                        "struct_name": "0x4170746f73436f696e"
+                    },
+                    {
+                        "account_address": "0xabc",
+                        "module_name": "0x746f6b656e",
+                        "struct_name": "0x546f6b656e"
+                    }
+                ],
+                "rewards": [
+                    {
+                        "remaining_reward": "100",
+                        "reward_per_day": "10",
+                        "reward_per_share_decimal": "0"
+                    },
+                    {
+                        "remaining_reward": "5",
+                        "reward_per_day": "20",
+                        "reward_per_share_decimal": "0"
+                    }
+                ],
+                "share": "1000",
+                "timestamp": "0"
+            }"#,
+        )
+        .unwrap();
+
+        let into_rewards = |json: Value| {
+            serde_json::from_value::<RewardItem>(json)
+                .unwrap()
+                .into_rewards(FarmType::DepositFarming)
+        };
+
+        let rewards = into_rewards(json.clone()).unwrap();
+        assert_eq!(rewards.len(), 2);
+        assert_eq!(rewards[0].token_address, "0x1::aptos_coin::AptosCoin");
+        assert_eq!(rewards[0].daily_emission(), 10);
+        assert_eq!(rewards[1].token_address, "0xabc::token::Token");
+        assert_eq!(rewards[1].daily_emission(), 5);
+        for reward in &rewards {
+            assert_eq!(reward.farm_type, FarmType::DepositFarming);
+            assert_eq!(reward.total_shares, 1000);
+        }
+
+        let mut bad_share = json.clone();
+        bad_share["share"] = "-1".into();
+        assert!(into_rewards(bad_share).is_err());
+        let mut missing_type = json;
+        missing_type["reward_types"].as_array_mut().unwrap().pop();
+        assert!(into_rewards(missing_type).is_err());
+    }
+
+    #[test]
+    fn test_is_price_not_found() {
+        assert!(is_price_not_found("API error Error(VmError): Move abort in 0xabc::oracle: 0x1"));
+        assert!(!is_price_not_found("error sending request for url"));
+        assert!(!is_price_not_found("Move abort in 0xabc::reserve: 0x1"));
+    }
+
+    #[tokio::test]
+    async fn test_reward_price_transport_error() {
+        let client = Client {
+            rest_client: rest_client::Client::new(Url::from_str("http://127.0.0.1:1").unwrap()),
+            wallet: LocalAccount::from_private_key(&format!("0x{}", "11".repeat(32)), 0).unwrap(),
+            chain_id: ChainId::test(),
+            indexer_url: "",
+            contract_address: AccountAddress::ONE,
+        };
+
+        let token = "0x1::aptos_coin::AptosCoin".to_string();
+        assert!(client.get_reward_price(&token).await.is_err());
+    }
+}
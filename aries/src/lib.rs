@@ -5,4 +5,5 @@ mod utils;
 
 pub use client::*;
 // pub use looper::*;
+pub use tx_options::{GasUnitPrice, TxOptions, TxOptionsError};
 pub use types::*;
@@ -1,8 +1,18 @@
-use aptos_sdk::move_types::account_address::AccountAddress;
+use aptos_sdk::{move_types::account_address::AccountAddress, rest_client::aptos_api_types::Event};
 use serde::{Deserialize, Serialize};
 
 pub const BORROW_DECIMALS: u32 = 18;
 
+/// Outcome of simulating a transaction without submitting it
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    pub success: bool,
+    pub vm_status: String,
+    pub gas_used: u64,
+    pub gas_unit_price: u64,
+    pub events: Vec<Event>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Profile {
     pub wallet_address: AccountAddress,
@@ -250,11 +260,7 @@ impl ReserveDetail {
     }
 
     pub fn get_reward_apr(&self, reward: &Reward, reserve_price: u64, reward_price: u64) -> f64 {
-        let daily_reward = reward
-            .reward_per_day
-            .parse::<u64>()
-            .unwrap_or_default()
-            .min(reward.remaining_reward.parse().unwrap_or_default());
+        let daily_reward = reward.daily_emission();
 
         let (dep_rate, bor_rate) = self.get_exchange_rates();
         let reserve_amount = match reward.farm_type {
@@ -306,7 +312,7 @@ impl FarmType {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Reward {
     #[serde(skip_deserializing)]
     pub token_address: String,
@@ -319,6 +325,30 @@ pub struct Reward {
     pub reward_per_share_decimal: String,
 }
 
+impl Reward {
+    pub fn remaining(&self) -> u64 {
+        self.remaining_reward.parse().unwrap_or_default()
+    }
+
+    /// Reward distributed over the next day, capped by the remaining reward
+    pub fn daily_emission(&self) -> u64 {
+        self.reward_per_day
+            .parse::<u64>()
+            .unwrap_or_default()
+            .min(self.remaining())
+    }
+}
+
+/// APR of one reward token of a reserve farm
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RewardApr {
+    pub token: String,
+    /// `None` when the oracle has no price for the reward token
+    pub apr: Option<f64>,
+    pub daily_emission: u64,
+    pub remaining: u64,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct TableObject {
     pub handle: AccountAddress,
@@ -359,7 +389,7 @@ impl TableKey {
 
 #[cfg(test)]
 mod tests {
-    use super::ReserveDetail;
+    use super::{Reward, ReserveDetail};
 
     #[test]
     fn test_reserve() {
@@ -410,4 +440,19 @@ mod tests {
 
         assert_eq!(reserve.get_borrow_amount_without_fee(1000), 999); // fee 0.1%
     }
+
+    #[test]
+    fn test_reward_daily_emission() {
+        let reward: Reward = serde_json::from_str(
+            r#"{
+                "remaining_reward": "500",
+                "reward_per_day": "2000",
+                "reward_per_share_decimal": "0"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(reward.remaining(), 500);
+        assert_eq!(reward.daily_emission(), 500);
+    }
 }
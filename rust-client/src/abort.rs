@@ -0,0 +1,584 @@
+use std::{fmt, str::FromStr};
+
+use aptos_sdk::move_types::account_address::AccountAddress;
+
+/// Abort codes of `moneyfi::vault`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultAbort {
+    AlreadyInitialized,
+    DepositNotAllowed,
+    WithdrawNotAllowed,
+    AssetNotSupported,
+    Deprecated,
+}
+
+impl VaultAbort {
+    pub fn from_reason(reason: u64) -> Option<Self> {
+        match reason {
+            1 => Some(VaultAbort::AlreadyInitialized),
+            2 => Some(VaultAbort::DepositNotAllowed),
+            3 => Some(VaultAbort::WithdrawNotAllowed),
+            4 => Some(VaultAbort::AssetNotSupported),
+            5 => Some(VaultAbort::Deprecated),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VaultAbort::AlreadyInitialized => "E_ALREADY_INITIALIZED",
+            VaultAbort::DepositNotAllowed => "E_DEPOSIT_NOT_ALLOWED",
+            VaultAbort::WithdrawNotAllowed => "E_WITHDRAW_NOT_ALLOWED",
+            VaultAbort::AssetNotSupported => "E_ASSET_NOT_SUPPORTED",
+            VaultAbort::Deprecated => "E_DEPRECATED",
+        }
+    }
+}
+
+/// Abort codes of `moneyfi::strategy_aries`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyAriesAbort {
+    VaultExists,
+    ExceedCapacity,
+    UnsupportedAsset,
+    PoolNotExist,
+    VaultNotExists,
+}
+
+impl StrategyAriesAbort {
+    pub fn from_reason(reason: u64) -> Option<Self> {
+        match reason {
+            1 => Some(StrategyAriesAbort::VaultExists),
+            2 => Some(StrategyAriesAbort::ExceedCapacity),
+            3 => Some(StrategyAriesAbort::UnsupportedAsset),
+            4 => Some(StrategyAriesAbort::PoolNotExist),
+            5 => Some(StrategyAriesAbort::VaultNotExists),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyAriesAbort::VaultExists => "E_VAULT_EXISTS",
+            StrategyAriesAbort::ExceedCapacity => "E_EXCEED_CAPACITY",
+            StrategyAriesAbort::UnsupportedAsset => "E_UNSUPPORTED_ASSET",
+            StrategyAriesAbort::PoolNotExist => "E_POOL_NOT_EXIST",
+            StrategyAriesAbort::VaultNotExists => "E_VAULT_NOT_EXISTS",
+        }
+    }
+}
+
+/// Abort codes of `moneyfi::access_control`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessControlAbort {
+    AlreadyInitialized,
+    NotAuthorized,
+    EmptyRoles,
+    InvalidRegistryState,
+    ConflictRoles,
+    RegistryLocked,
+}
+
+impl AccessControlAbort {
+    pub fn from_reason(reason: u64) -> Option<Self> {
+        match reason {
+            1 => Some(AccessControlAbort::AlreadyInitialized),
+            2 => Some(AccessControlAbort::NotAuthorized),
+            3 => Some(AccessControlAbort::EmptyRoles),
+            4 => Some(AccessControlAbort::InvalidRegistryState),
+            5 => Some(AccessControlAbort::ConflictRoles),
+            6 => Some(AccessControlAbort::RegistryLocked),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccessControlAbort::AlreadyInitialized => "E_ALREADY_INITIALIZED",
+            AccessControlAbort::NotAuthorized => "E_NOT_AUTHORIZED",
+            AccessControlAbort::EmptyRoles => "E_EMPTY_ROLES",
+            AccessControlAbort::InvalidRegistryState => "E_INVALID_REGISTRY_STATE",
+            AccessControlAbort::ConflictRoles => "E_CONFLICT_ROLES",
+            AccessControlAbort::RegistryLocked => "E_REGISTRY_LOCKED",
+        }
+    }
+}
+
+/// Abort codes of `moneyfi::wallet_account`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletAccountAbort {
+    WalletAccountExists,
+    WalletAccountNotExists,
+    NotAptosWalletAccount,
+    NotOwner,
+    WalletAccountNotConnected,
+    WalletAccountAlreadyConnected,
+    InvalidArgument,
+    StrategyDataNotExists,
+    ReferrerWalletIdExists,
+    Deprecated,
+}
+
+impl WalletAccountAbort {
+    pub fn from_reason(reason: u64) -> Option<Self> {
+        match reason {
+            1 => Some(WalletAccountAbort::WalletAccountExists),
+            2 => Some(WalletAccountAbort::WalletAccountNotExists),
+            3 => Some(WalletAccountAbort::NotAptosWalletAccount),
+            4 => Some(WalletAccountAbort::NotOwner),
+            5 => Some(WalletAccountAbort::WalletAccountNotConnected),
+            6 => Some(WalletAccountAbort::WalletAccountAlreadyConnected),
+            7 => Some(WalletAccountAbort::InvalidArgument),
+            8 => Some(WalletAccountAbort::StrategyDataNotExists),
+            9 => Some(WalletAccountAbort::ReferrerWalletIdExists),
+            10 => Some(WalletAccountAbort::Deprecated),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WalletAccountAbort::WalletAccountExists => "E_WALLET_ACCOUNT_EXISTS",
+            WalletAccountAbort::WalletAccountNotExists => "E_WALLET_ACCOUNT_NOT_EXISTS",
+            WalletAccountAbort::NotAptosWalletAccount => "E_NOT_APTOS_WALLET_ACCOUNT",
+            WalletAccountAbort::NotOwner => "E_NOT_OWNER",
+            WalletAccountAbort::WalletAccountNotConnected => "E_WALLET_ACCOUNT_NOT_CONNECTED",
+            WalletAccountAbort::WalletAccountAlreadyConnected => {
+                "E_WALLET_ACCOUNT_ALREADY_CONNECTED"
+            }
+            WalletAccountAbort::InvalidArgument => "E_INVALID_ARGUMENT",
+            WalletAccountAbort::StrategyDataNotExists => "E_STRATEGY_DATA_NOT_EXISTS",
+            WalletAccountAbort::ReferrerWalletIdExists => "E_REFERRER_WALLET_ID_EXISTS",
+            WalletAccountAbort::Deprecated => "E_DEPRECATED",
+        }
+    }
+}
+
+/// Abort codes of the deprecated `moneyfi::strategy` router
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyAbort {
+    UnknownStrategy,
+    NotSupportedByStrategy,
+}
+
+impl StrategyAbort {
+    pub fn from_reason(reason: u64) -> Option<Self> {
+        match reason {
+            1 => Some(StrategyAbort::UnknownStrategy),
+            2 => Some(StrategyAbort::NotSupportedByStrategy),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyAbort::UnknownStrategy => "E_UNKNOWN_STRATEGY",
+            StrategyAbort::NotSupportedByStrategy => "E_NOT_SUPPORTED_BY_STRATEGY",
+        }
+    }
+}
+
+/// Abort codes of `moneyfi::strategy_hyperion`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyHyperionAbort {
+    StrategyDataNotExists,
+    PositionNotExists,
+}
+
+impl StrategyHyperionAbort {
+    pub fn from_reason(reason: u64) -> Option<Self> {
+        match reason {
+            1 => Some(StrategyHyperionAbort::StrategyDataNotExists),
+            3 => Some(StrategyHyperionAbort::PositionNotExists),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyHyperionAbort::StrategyDataNotExists => "E_HYPERION_STRATEGY_DATA_NOT_EXISTS",
+            StrategyHyperionAbort::PositionNotExists => "E_HYPERION_POSITION_NOT_EXISTS",
+        }
+    }
+}
+
+/// Abort codes of `moneyfi::strategy_thala`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyThalaAbort {
+    StrategyDataNotExists,
+    PositionNotExists,
+    InvalidAsset,
+    InvalidAmount,
+}
+
+impl StrategyThalaAbort {
+    pub fn from_reason(reason: u64) -> Option<Self> {
+        match reason {
+            1 => Some(StrategyThalaAbort::StrategyDataNotExists),
+            2 => Some(StrategyThalaAbort::PositionNotExists),
+            3 => Some(StrategyThalaAbort::InvalidAsset),
+            4 => Some(StrategyThalaAbort::InvalidAmount),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyThalaAbort::StrategyDataNotExists => "E_THALA_STRATEGY_DATA_NOT_EXISTS",
+            StrategyThalaAbort::PositionNotExists => "E_THALA_POSITION_NOT_EXISTS",
+            StrategyThalaAbort::InvalidAsset => "E_INVALID_ASSET",
+            StrategyThalaAbort::InvalidAmount => "E_INVALID_AMOUNT",
+        }
+    }
+}
+
+/// Abort codes of `moneyfi::strategy_tapp`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyTappAbort {
+    StrategyDataNotExists,
+    PositionNotExists,
+    InvalidAsset,
+}
+
+impl StrategyTappAbort {
+    pub fn from_reason(reason: u64) -> Option<Self> {
+        match reason {
+            1 => Some(StrategyTappAbort::StrategyDataNotExists),
+            2 => Some(StrategyTappAbort::PositionNotExists),
+            3 => Some(StrategyTappAbort::InvalidAsset),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyTappAbort::StrategyDataNotExists => "E_TAPP_STRATEGY_DATA_NOT_EXISTS",
+            StrategyTappAbort::PositionNotExists => "E_TAPP_POSITION_NOT_EXISTS",
+            StrategyTappAbort::InvalidAsset => "E_INVALID_ASSET",
+        }
+    }
+}
+
+/// Category bits of a `std::error` abort code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    InvalidArgument,
+    OutOfRange,
+    InvalidState,
+    Unauthenticated,
+    PermissionDenied,
+    NotFound,
+    Aborted,
+    AlreadyExists,
+    ResourceExhausted,
+    Cancelled,
+    Internal,
+    NotImplemented,
+    Unavailable,
+}
+
+impl ErrorCategory {
+    /// Reads the category of a `category << 16 | reason` code
+    pub fn from_code(code: u64) -> Option<Self> {
+        if code >> 24 != 0 {
+            return None;
+        }
+
+        match code >> 16 {
+            0x1 => Some(ErrorCategory::InvalidArgument),
+            0x2 => Some(ErrorCategory::OutOfRange),
+            0x3 => Some(ErrorCategory::InvalidState),
+            0x4 => Some(ErrorCategory::Unauthenticated),
+            0x5 => Some(ErrorCategory::PermissionDenied),
+            0x6 => Some(ErrorCategory::NotFound),
+            0x7 => Some(ErrorCategory::Aborted),
+            0x8 => Some(ErrorCategory::AlreadyExists),
+            0x9 => Some(ErrorCategory::ResourceExhausted),
+            0xa => Some(ErrorCategory::Cancelled),
+            0xb => Some(ErrorCategory::Internal),
+            0xc => Some(ErrorCategory::NotImplemented),
+            0xd => Some(ErrorCategory::Unavailable),
+            _ => None,
+        }
+    }
+
+    /// Name of the `std::error` function raising the category
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCategory::InvalidArgument => "invalid_argument",
+            ErrorCategory::OutOfRange => "out_of_range",
+            ErrorCategory::InvalidState => "invalid_state",
+            ErrorCategory::Unauthenticated => "unauthenticated",
+            ErrorCategory::PermissionDenied => "permission_denied",
+            ErrorCategory::NotFound => "not_found",
+            ErrorCategory::Aborted => "aborted",
+            ErrorCategory::AlreadyExists => "already_exists",
+            ErrorCategory::ResourceExhausted => "resource_exhausted",
+            ErrorCategory::Cancelled => "cancelled",
+            ErrorCategory::Internal => "internal",
+            ErrorCategory::NotImplemented => "not_implemented",
+            ErrorCategory::Unavailable => "unavailable",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Code of an `assert!` without an abort code
+pub const UNSPECIFIED_ABORT_CODE: u64 = 0xca26_cbd9_be0b_0000;
+
+/// Named reason of a Move abort raised by one of the MoneyFi modules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    Vault(VaultAbort),
+    WalletAccount(WalletAccountAbort),
+    AccessControl(AccessControlAbort),
+    Strategy(StrategyAbort),
+    StrategyAries(StrategyAriesAbort),
+    StrategyHyperion(StrategyHyperionAbort),
+    StrategyThala(StrategyThalaAbort),
+    StrategyTapp(StrategyTappAbort),
+    /// `assert!` without an abort code
+    Unspecified,
+    /// The abort code is not one of the known constants of the module
+    Unknown,
+}
+
+impl AbortReason {
+    /// Maps an abort code to the named constant of `module`.
+    /// The `std::error` category bits of `code` are ignored.
+    pub fn decode(module: &str, code: u64) -> Self {
+        if code == UNSPECIFIED_ABORT_CODE {
+            return AbortReason::Unspecified;
+        }
+
+        let reason = code & 0xffff;
+        let decoded = match module {
+            "vault" => VaultAbort::from_reason(reason).map(AbortReason::Vault),
+            "wallet_account" => {
+                WalletAccountAbort::from_reason(reason).map(AbortReason::WalletAccount)
+            }
+            "access_control" => {
+                AccessControlAbort::from_reason(reason).map(AbortReason::AccessControl)
+            }
+            "strategy" => StrategyAbort::from_reason(reason).map(AbortReason::Strategy),
+            "strategy_aries" => {
+                StrategyAriesAbort::from_reason(reason).map(AbortReason::StrategyAries)
+            }
+            "strategy_hyperion" => {
+                StrategyHyperionAbort::from_reason(reason).map(AbortReason::StrategyHyperion)
+            }
+            "strategy_thala" => {
+                StrategyThalaAbort::from_reason(reason).map(AbortReason::StrategyThala)
+            }
+            "strategy_tapp" => {
+                StrategyTappAbort::from_reason(reason).map(AbortReason::StrategyTapp)
+            }
+            _ => None,
+        };
+
+        decoded.unwrap_or(AbortReason::Unknown)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AbortReason::Vault(v) => v.name(),
+            AbortReason::WalletAccount(v) => v.name(),
+            AbortReason::AccessControl(v) => v.name(),
+            AbortReason::Strategy(v) => v.name(),
+            AbortReason::StrategyAries(v) => v.name(),
+            AbortReason::StrategyHyperion(v) => v.name(),
+            AbortReason::StrategyThala(v) => v.name(),
+            AbortReason::StrategyTapp(v) => v.name(),
+            AbortReason::Unspecified => "UNSPECIFIED",
+            AbortReason::Unknown => "UNKNOWN",
+        }
+    }
+
+    /// Module declaring the constant, `None` for unspecified and unknown reasons
+    pub fn module(&self) -> Option<&'static str> {
+        let module = match self {
+            AbortReason::Vault(_) => "vault",
+            AbortReason::WalletAccount(_) => "wallet_account",
+            AbortReason::AccessControl(_) => "access_control",
+            AbortReason::Strategy(_) => "strategy",
+            AbortReason::StrategyAries(_) => "strategy_aries",
+            AbortReason::StrategyHyperion(_) => "strategy_hyperion",
+            AbortReason::StrategyThala(_) => "strategy_thala",
+            AbortReason::StrategyTapp(_) => "strategy_tapp",
+            AbortReason::Unspecified | AbortReason::Unknown => return None,
+        };
+
+        Some(module)
+    }
+}
+
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.module() {
+            Some(module) => write!(f, "{}::{}", module, self.name()),
+            None => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// A Move abort decoded from the VM status of a failed or simulated transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveAbort {
+    pub address: AccountAddress,
+    pub module: String,
+    pub code: u64,
+    /// `None` for codes not built with `std::error`
+    pub category: Option<ErrorCategory>,
+    /// Only decoded for modules published at the MoneyFi contract address
+    pub reason: AbortReason,
+}
+
+impl MoveAbort {
+    /// Returns `None` when the transaction did not fail with a Move abort
+    pub fn from_vm_status(vm_status: &str, contract_address: AccountAddress) -> Option<Self> {
+        let status = parse_move_abort(vm_status)?;
+        let reason = if status.address == contract_address {
+            AbortReason::decode(&status.module, status.code)
+        } else if status.code == UNSPECIFIED_ABORT_CODE {
+            AbortReason::Unspecified
+        } else {
+            AbortReason::Unknown
+        };
+
+        Some(MoveAbort {
+            category: ErrorCategory::from_code(status.code),
+            address: status.address,
+            module: status.module,
+            code: status.code,
+            reason,
+        })
+    }
+}
+
+impl fmt::Display for MoveAbort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}: ", self.address, self.module)?;
+        if self.reason != AbortReason::Unknown {
+            write!(f, "{} ", self.reason.name())?;
+        }
+        write!(f, "({:#x}", self.code)?;
+        if let Some(category) = self.category {
+            write!(f, ", {}", category)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Location and code of a Move abort
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MoveAbortStatus {
+    address: AccountAddress,
+    module: String,
+    code: u64,
+}
+
+/// Parses a VM status such as `Move abort in 0x1::vault: 0x50002` or
+/// `Move abort in 0x1::vault: E_DEPOSIT_NOT_ALLOWED(0x50002): ...`
+fn parse_move_abort(vm_status: &str) -> Option<MoveAbortStatus> {
+    let (_, rest) = vm_status.split_once("Move abort in ")?;
+    let (location, rest) = rest.split_once(": ")?;
+    let (address, module) = location.rsplit_once("::")?;
+    let address = AccountAddress::from_str(address).ok()?;
+
+    let code = match rest.find('(') {
+        Some(start) if !rest.starts_with("0x") => {
+            let end = rest[start..].find(')')? + start;
+            parse_code(&rest[start + 1..end])?
+        }
+        _ => parse_code(rest.split(|c: char| c == ':' || c.is_whitespace()).next()?)?,
+    };
+
+    Some(MoveAbortStatus {
+        address,
+        module: module.to_string(),
+        code,
+    })
+}
+
+fn parse_code(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_move_abort() {
+        let status = parse_move_abort("Move abort in 0x1::vault: 0x50002").unwrap();
+        assert_eq!(status.module, "vault");
+        assert_eq!(status.code, 0x50002);
+        assert_eq!(
+            AbortReason::decode(&status.module, status.code),
+            AbortReason::Vault(VaultAbort::DepositNotAllowed)
+        );
+
+        let status = parse_move_abort(
+            "Move abort in 0x97c9ffc7143c5585090f9ade67d19ac95f3b3e7008ed86c73c947637e2862f56::access_control: E_REGISTRY_LOCKED(0x50006): ",
+        )
+        .unwrap();
+        assert_eq!(status.module, "access_control");
+        assert_eq!(
+            AbortReason::decode(&status.module, status.code),
+            AbortReason::AccessControl(AccessControlAbort::RegistryLocked)
+        );
+
+        assert!(parse_move_abort("Out of gas").is_none());
+    }
+
+    #[test]
+    fn test_move_abort_from_vm_status() {
+        let contract = AccountAddress::from_str("0xabc").unwrap();
+
+        let abort =
+            MoveAbort::from_vm_status("Move abort in 0xabc::strategy_aries: 0x60005", contract)
+                .unwrap();
+        assert_eq!(
+            abort.reason,
+            AbortReason::StrategyAries(StrategyAriesAbort::VaultNotExists)
+        );
+        assert_eq!(abort.category, Some(ErrorCategory::NotFound));
+        assert_eq!(
+            abort.reason.to_string(),
+            "strategy_aries::E_VAULT_NOT_EXISTS"
+        );
+        assert_eq!(
+            abort.to_string(),
+            format!("{contract}::strategy_aries: E_VAULT_NOT_EXISTS (0x60005, not_found)")
+        );
+
+        let abort =
+            MoveAbort::from_vm_status("Move abort in 0xabc::wallet_account: 0x80001", contract)
+                .unwrap();
+        assert_eq!(
+            abort.reason,
+            AbortReason::WalletAccount(WalletAccountAbort::WalletAccountExists)
+        );
+        assert_eq!(abort.category, Some(ErrorCategory::AlreadyExists));
+
+        // same module name published by another account
+        let abort =
+            MoveAbort::from_vm_status("Move abort in 0x1::vault: 0x50002", contract).unwrap();
+        assert_eq!(abort.reason, AbortReason::Unknown);
+        assert_eq!(abort.category, Some(ErrorCategory::PermissionDenied));
+
+        let abort =
+            MoveAbort::from_vm_status("Move abort in 0xabc::vault: 0xca26cbd9be0b0000", contract)
+                .unwrap();
+        assert_eq!(abort.reason, AbortReason::Unspecified);
+        assert_eq!(abort.category, None);
+    }
+}
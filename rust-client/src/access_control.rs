@@ -0,0 +1,560 @@
+use std::{fmt, sync::Arc};
+
+use aptos_sdk::{
+    bcs, move_types::account_address::AccountAddress, types::transaction::EntryFunction,
+};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    Client, Error, OrderedMap, OrderedMapEntry, Receipt, Result, SimulationResult, TxOptions,
+    events::{RemoveAccountEvent, UpdateAccountEvent},
+    utils::{deserialize_bytes_from_hexstring, deserialize_number_from_string, expect_len},
+};
+
+const MODULE_NAME: &str = "access_control";
+
+/// Roles of `access_control.move`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Role {
+    /// Changes system configurations and unlocks the registry
+    Admin = 1,
+    /// Adds and removes accounts while the registry is unlocked
+    RoleManager = 2,
+    /// Deposits and withdraws funds to and from strategies
+    ServiceAccount = 3,
+    /// Configures and withdraws fees
+    FeeManager = 4,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [
+        Role::Admin,
+        Role::RoleManager,
+        Role::ServiceAccount,
+        Role::FeeManager,
+    ];
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Role::ALL.into_iter().find(|role| role.id() == id)
+    }
+
+    pub fn id(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Admin => "ADMIN",
+            Role::RoleManager => "ROLE_MANAGER",
+            Role::ServiceAccount => "SERVICE_ACCOUNT",
+            Role::FeeManager => "FEE_MANAGER",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Role ids as stored on-chain, split into known roles and ids this client does not know.
+/// Unknown ids, including 0, are accepted by `upsert_account` but grant nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoleIds {
+    pub roles: Vec<Role>,
+    pub unknown: Vec<u8>,
+}
+
+impl RoleIds {
+    pub fn from_ids(ids: impl IntoIterator<Item = u8>) -> Self {
+        let mut role_ids = RoleIds::default();
+        for id in ids {
+            match Role::from_id(id) {
+                Some(role) => role_ids.roles.push(role),
+                None => role_ids.unknown.push(id),
+            }
+        }
+
+        role_ids
+    }
+}
+
+impl<'de> Deserialize<'de> for RoleIds {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(RoleIds::from_ids(deserialize_bytes_from_hexstring(
+            deserializer,
+        )?))
+    }
+}
+
+/// An item of `access_control::get_accounts`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "AccountRolesJson")]
+pub struct AccountRoles {
+    pub account: AccountAddress,
+    pub roles: Vec<Role>,
+    /// Stored role ids without a matching `Role`
+    pub unknown_role_ids: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct AccountRolesJson {
+    account: AccountAddress,
+    roles: RoleIds,
+}
+
+impl From<AccountRolesJson> for AccountRoles {
+    fn from(json: AccountRolesJson) -> Self {
+        AccountRoles {
+            account: json.account,
+            roles: json.roles.roles,
+            unknown_role_ids: json.roles.unknown,
+        }
+    }
+}
+
+/// The `access_control::Registry` resource
+#[derive(Debug, Clone)]
+pub struct Registry {
+    pub accounts: Vec<AccountRoles>,
+    /// Writes are allowed until this timestamp, in seconds
+    pub locked_at: u64,
+}
+
+#[derive(Deserialize)]
+struct RegistryResource {
+    accounts: OrderedMap<AccountAddress, RoleIds>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    locked_at: u64,
+}
+
+impl From<RegistryResource> for Registry {
+    fn from(resource: RegistryResource) -> Self {
+        Registry {
+            accounts: resource
+                .accounts
+                .entries
+                .into_iter()
+                .map(|OrderedMapEntry { key, value }| AccountRoles {
+                    account: key,
+                    roles: value.roles,
+                    unknown_role_ids: value.unknown,
+                })
+                .collect(),
+            locked_at: resource.locked_at,
+        }
+    }
+}
+
+impl Registry {
+    pub fn roles(&self, account: AccountAddress) -> &[Role] {
+        self.accounts
+            .iter()
+            .find(|item| item.account == account)
+            .map(|item| item.roles.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn has_role(&self, account: AccountAddress, role: Role) -> bool {
+        self.roles(account).contains(&role)
+    }
+
+    pub fn is_unlocked(&self, now_seconds: u64) -> bool {
+        self.locked_at > now_seconds
+    }
+
+    /// Same check as `access_control::must_be_*`
+    pub fn check_role(&self, account: AccountAddress, role: Role) -> Result<()> {
+        if !self.has_role(account, role) {
+            return Err(Error::MissingRole { account, role });
+        }
+
+        Ok(())
+    }
+
+    pub fn check_unlock(&self, sender: AccountAddress) -> Result<()> {
+        self.check_role(sender, Role::Admin)
+    }
+
+    /// Runs the checks of `access_control::upsert_account`, returns the roles as stored on-chain
+    pub fn check_upsert(
+        &self,
+        sender: AccountAddress,
+        account: AccountAddress,
+        roles: &[Role],
+        now_seconds: u64,
+    ) -> Result<Vec<Role>> {
+        // the admin adds the first role manager, then only role managers can change accounts
+        if self.accounts.len() == 1 {
+            self.check_role(sender, Role::Admin)?;
+        } else {
+            self.check_role(sender, Role::RoleManager)?;
+        }
+
+        let mut valid_roles = Vec::with_capacity(roles.len());
+        for role in roles {
+            if !valid_roles.contains(role) {
+                valid_roles.push(*role);
+            }
+        }
+        if valid_roles.is_empty() {
+            return Err(Error::InvalidArgument("roles are empty".into()));
+        }
+        if valid_roles.contains(&Role::Admin) && valid_roles.contains(&Role::RoleManager) {
+            return Err(Error::InvalidArgument(
+                "an account cannot be both ADMIN and ROLE_MANAGER".into(),
+            ));
+        }
+
+        self.check_unlocked(now_seconds)?;
+
+        let mut next = self.clone();
+        match next
+            .accounts
+            .iter_mut()
+            .find(|item| item.account == account)
+        {
+            Some(item) => {
+                item.roles = valid_roles.clone();
+                item.unknown_role_ids.clear();
+            }
+            None => next.accounts.push(AccountRoles {
+                account,
+                roles: valid_roles.clone(),
+                unknown_role_ids: vec![],
+            }),
+        }
+        next.validate_state()?;
+
+        Ok(valid_roles)
+    }
+
+    /// Runs the checks of `access_control::remove_account`
+    pub fn check_remove(
+        &self,
+        sender: AccountAddress,
+        account: AccountAddress,
+        now_seconds: u64,
+    ) -> Result<()> {
+        self.check_role(sender, Role::RoleManager)?;
+        self.check_unlocked(now_seconds)?;
+
+        let mut next = self.clone();
+        let len = next.accounts.len();
+        next.accounts.retain(|item| item.account != account);
+        if next.accounts.len() == len {
+            return Err(Error::InvalidArgument(format!(
+                "{} is not in the registry",
+                account
+            )));
+        }
+
+        next.validate_state()
+    }
+
+    fn check_unlocked(&self, now_seconds: u64) -> Result<()> {
+        if !self.is_unlocked(now_seconds) {
+            return Err(Error::NotAllowed(
+                "the registry is locked, an ADMIN must call unlock_registry first".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// At least one admin, and a role manager once there is more than one account
+    fn validate_state(&self) -> Result<()> {
+        let count = |role| {
+            self.accounts
+                .iter()
+                .filter(|item| item.roles.contains(&role))
+                .count()
+        };
+
+        if count(Role::Admin) == 0 {
+            return Err(Error::InvalidArgument(
+                "the registry would be left without an ADMIN".into(),
+            ));
+        }
+        if self.accounts.len() > 1 && count(Role::RoleManager) == 0 {
+            return Err(Error::InvalidArgument(
+                "the registry would be left without a ROLE_MANAGER".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Role registry client. Writes follow the on-chain flow: an admin unlocks the registry
+/// for a while, then the role manager upserts or removes accounts.
+/// Every write is checked against the registry before it is sent.
+pub struct AccessControl {
+    client: Arc<Client>,
+}
+
+impl AccessControl {
+    pub fn new(client: Arc<Client>) -> Self {
+        AccessControl { client }
+    }
+
+    pub async fn get_accounts(&self) -> Result<Vec<AccountRoles>> {
+        let function = "get_accounts";
+        let data = self.client.view(MODULE_NAME, function, vec![]).await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+
+    pub async fn get_registry(&self) -> Result<Registry> {
+        let data = self
+            .client
+            .resource(self.client.contract_address, MODULE_NAME, "Registry")
+            .await?;
+
+        Ok(serde_json::from_value::<RegistryResource>(data)?.into())
+    }
+
+    /// Roles of the connected signer
+    pub async fn get_signer_roles(&self) -> Result<Vec<Role>> {
+        let sender = self.sender()?;
+
+        Ok(self.get_registry().await?.roles(sender).to_vec())
+    }
+
+    /// Fails with `Error::MissingRole` unless `account` has `role`
+    pub async fn check_role(&self, account: AccountAddress, role: Role) -> Result<()> {
+        self.get_registry().await?.check_role(account, role)
+    }
+
+    /// Fails with `Error::MissingRole` unless the connected signer has every role
+    pub async fn check_signer_roles(&self, roles: &[Role]) -> Result<()> {
+        let sender = self.sender()?;
+        let registry = self.get_registry().await?;
+
+        roles
+            .iter()
+            .try_for_each(|role| registry.check_role(sender, *role))
+    }
+
+    pub fn unlock_registry_payload(&self, timeout_secs: u64) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "unlock_registry",
+            vec![bcs::to_bytes(&timeout_secs)?],
+        )
+    }
+
+    /// Opens the registry for writes during `timeout_secs`, requires the admin role
+    pub async fn unlock_registry(
+        &self,
+        timeout_secs: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        self.get_registry().await?.check_unlock(self.sender()?)?;
+        let efn = self.unlock_registry_payload(timeout_secs)?;
+
+        self.client.send_tx(efn, opts).await
+    }
+
+    pub async fn simulate_unlock_registry(
+        &self,
+        timeout_secs: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.unlock_registry_payload(timeout_secs)?;
+
+        self.client.simulate(efn, opts).await
+    }
+
+    pub fn upsert_account_payload(
+        &self,
+        account: AccountAddress,
+        roles: &[Role],
+    ) -> Result<EntryFunction> {
+        let role_ids = roles.iter().map(Role::id).collect::<Vec<_>>();
+
+        self.client.entry_function(
+            MODULE_NAME,
+            "upsert_account",
+            vec![bcs::to_bytes(&account)?, bcs::to_bytes(&role_ids)?],
+        )
+    }
+
+    /// Replaces the roles of `account`, adding it if needed
+    pub async fn upsert_account(
+        &self,
+        account: AccountAddress,
+        roles: &[Role],
+        opts: Option<TxOptions>,
+    ) -> Result<(Receipt, UpdateAccountEvent)> {
+        let now_seconds = self.ledger_timestamp_secs().await?;
+        let roles =
+            self.get_registry()
+                .await?
+                .check_upsert(self.sender()?, account, roles, now_seconds)?;
+        let efn = self.upsert_account_payload(account, &roles)?;
+        self.client.send_for_event(efn, opts).await
+    }
+
+    pub async fn simulate_upsert_account(
+        &self,
+        account: AccountAddress,
+        roles: &[Role],
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.upsert_account_payload(account, roles)?;
+
+        self.client.simulate(efn, opts).await
+    }
+
+    pub fn remove_account_payload(&self, account: AccountAddress) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "remove_account",
+            vec![bcs::to_bytes(&account)?],
+        )
+    }
+
+    pub async fn remove_account(
+        &self,
+        account: AccountAddress,
+        opts: Option<TxOptions>,
+    ) -> Result<(Receipt, RemoveAccountEvent)> {
+        let now_seconds = self.ledger_timestamp_secs().await?;
+        self.get_registry()
+            .await?
+            .check_remove(self.sender()?, account, now_seconds)?;
+        let efn = self.remove_account_payload(account)?;
+        self.client.send_for_event(efn, opts).await
+    }
+
+    pub async fn simulate_remove_account(
+        &self,
+        account: AccountAddress,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.remove_account_payload(account)?;
+
+        self.client.simulate(efn, opts).await
+    }
+
+    fn sender(&self) -> Result<AccountAddress> {
+        self.client.signer_address().ok_or(Error::NoAccount)
+    }
+
+    /// The registry lock is compared against the chain clock, not the local one
+    async fn ledger_timestamp_secs(&self) -> Result<u64> {
+        let info = self
+            .client
+            .aptos_client
+            .get_ledger_information()
+            .await?
+            .into_inner();
+
+        Ok(info.timestamp_usecs / 1_000_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(n: u8) -> AccountAddress {
+        AccountAddress::from_hex_literal(&format!("{:#x}", n)).unwrap()
+    }
+
+    #[test]
+    fn test_registry_checks() {
+        let admin = address(1);
+        let manager = address(2);
+        let mut registry = Registry {
+            accounts: vec![AccountRoles {
+                account: admin,
+                roles: vec![Role::Admin, Role::FeeManager],
+                unknown_role_ids: vec![],
+            }],
+            locked_at: 100,
+        };
+
+        // the admin adds the first role manager
+        assert_eq!(
+            registry
+                .check_upsert(admin, manager, &[Role::RoleManager, Role::RoleManager], 50)
+                .unwrap(),
+            vec![Role::RoleManager]
+        );
+        assert!(matches!(
+            registry.check_upsert(manager, manager, &[Role::RoleManager], 50),
+            Err(Error::MissingRole {
+                role: Role::Admin,
+                ..
+            })
+        ));
+        assert!(matches!(
+            registry.check_upsert(admin, manager, &[Role::RoleManager], 100),
+            Err(Error::NotAllowed(_))
+        ));
+        assert!(
+            registry
+                .check_upsert(admin, manager, &[Role::Admin, Role::RoleManager], 50)
+                .is_err()
+        );
+        // a second account without any role manager
+        assert!(
+            registry
+                .check_upsert(admin, manager, &[Role::ServiceAccount], 50)
+                .is_err()
+        );
+
+        registry.accounts.push(AccountRoles {
+            account: manager,
+            roles: vec![Role::RoleManager],
+            unknown_role_ids: vec![],
+        });
+        assert!(
+            registry
+                .check_upsert(admin, address(3), &[Role::FeeManager], 50)
+                .is_err()
+        );
+        assert!(
+            registry
+                .check_upsert(manager, address(3), &[Role::FeeManager], 50)
+                .is_ok()
+        );
+        assert!(registry.check_remove(manager, address(3), 50).is_err());
+        assert!(registry.check_remove(manager, admin, 50).is_err());
+        // back to a single admin
+        assert!(registry.check_remove(manager, manager, 50).is_ok());
+        assert!(registry.check_unlock(admin).is_ok());
+        assert!(registry.check_unlock(manager).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_registry() {
+        let registry: Registry = serde_json::from_value::<RegistryResource>(serde_json::json!({
+            "accounts": {
+                "entries": [{ "key": "0x1", "value": "0x010400" }]
+            },
+            "locked_at": "1700000000"
+        }))
+        .unwrap()
+        .into();
+
+        assert_eq!(
+            registry.roles(AccountAddress::ONE),
+            &[Role::Admin, Role::FeeManager]
+        );
+        assert_eq!(registry.accounts[0].unknown_role_ids, vec![0]);
+        assert_eq!(registry.locked_at, 1_700_000_000);
+
+        let accounts: Vec<AccountRoles> = serde_json::from_value(serde_json::json!([
+            { "account": "0x2", "roles": "0x0309" }
+        ]))
+        .unwrap();
+        assert_eq!(accounts[0].roles, vec![Role::ServiceAccount]);
+        assert_eq!(accounts[0].unknown_role_ids, vec![9]);
+    }
+}
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use aptos_sdk::{
+    bcs, move_types::account_address::AccountAddress, types::transaction::EntryFunction,
+};
+use serde::Deserialize;
+
+use crate::{
+    Client, Error, Receipt, Result, SimulationResult, TxOptions,
+    access_control::Role,
+    utils::{deserialize_number_from_string, deserialize_numbers_from_strings},
+};
+
+const MODULE_NAME: &str = "vault";
+
+/// Fees and referral percents are in bps, 100 => 1%
+pub const MAX_FEE_PERCENT: u64 = 10_000;
+
+/// Arguments of `vault::configure`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VaultConfig {
+    pub enable_deposit: bool,
+    pub enable_withdraw: bool,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub system_fee_percent: u64,
+    pub fee_recipient: AccountAddress,
+    /// [level_1, level_2, level_3, ...]
+    #[serde(deserialize_with = "deserialize_numbers_from_strings")]
+    pub referral_percents: Vec<u64>,
+}
+
+impl VaultConfig {
+    /// Checks the invariants asserted by `vault::configure`
+    pub fn validate(&self) -> Result<()> {
+        if self.system_fee_percent > MAX_FEE_PERCENT {
+            return Err(Error::InvalidArgument(format!(
+                "system fee percent {} exceeds {}",
+                self.system_fee_percent, MAX_FEE_PERCENT
+            )));
+        }
+
+        validate_referral_percents(&self.referral_percents)
+    }
+}
+
+/// Limits of a supported asset, a max of 0 means unlimited
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct AssetConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_deposit: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_deposit: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_withdraw: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_withdraw: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_exchange_rate: u64,
+}
+
+impl AssetConfig {
+    /// Checks the invariants asserted by `vault::upsert_supported_asset`
+    pub fn validate(&self) -> Result<()> {
+        if self.max_deposit > 0 && self.min_deposit > self.max_deposit {
+            return Err(Error::InvalidArgument(format!(
+                "min deposit {} is greater than max deposit {}",
+                self.min_deposit, self.max_deposit
+            )));
+        }
+        if self.max_withdraw > 0 && self.min_withdraw > self.max_withdraw {
+            return Err(Error::InvalidArgument(format!(
+                "min withdraw {} is greater than max withdraw {}",
+                self.min_withdraw, self.max_withdraw
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Same check as `wallet_account::validate_referral_percents`
+pub fn validate_referral_percents(percents: &[u64]) -> Result<()> {
+    let total = percents
+        .iter()
+        .try_fold(0u64, |total, percent| total.checked_add(*percent));
+
+    match total {
+        Some(total) if total < MAX_FEE_PERCENT => Ok(()),
+        _ => Err(Error::InvalidArgument(format!(
+            "referral percents {:?} must sum to less than {}",
+            percents, MAX_FEE_PERCENT
+        ))),
+    }
+}
+
+/// Vault configuration calls. `configure` requires the admin and fee manager roles,
+/// `upsert_supported_asset` the service account role.
+pub struct VaultAdmin {
+    client: Arc<Client>,
+}
+
+impl VaultAdmin {
+    pub fn new(client: Arc<Client>) -> Self {
+        VaultAdmin { client }
+    }
+
+    pub fn configure_payload(&self, config: &VaultConfig) -> Result<EntryFunction> {
+        config.validate()?;
+
+        self.client.entry_function(
+            MODULE_NAME,
+            "configure",
+            vec![
+                bcs::to_bytes(&config.enable_deposit)?,
+                bcs::to_bytes(&config.enable_withdraw)?,
+                bcs::to_bytes(&config.system_fee_percent)?,
+                bcs::to_bytes(&config.referral_percents)?,
+                bcs::to_bytes(&config.fee_recipient)?,
+            ],
+        )
+    }
+
+    pub async fn configure(
+        &self,
+        config: &VaultConfig,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let efn = self.configure_payload(config)?;
+        self.client
+            .send_restricted_tx(efn, &[Role::Admin, Role::FeeManager], opts)
+            .await
+    }
+
+    pub async fn simulate_configure(
+        &self,
+        config: &VaultConfig,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.configure_payload(config)?;
+
+        self.client.simulate(efn, opts).await
+    }
+
+    pub fn upsert_supported_asset_payload(
+        &self,
+        asset: AccountAddress,
+        config: &AssetConfig,
+    ) -> Result<EntryFunction> {
+        config.validate()?;
+
+        self.client.entry_function(
+            MODULE_NAME,
+            "upsert_supported_asset",
+            vec![
+                bcs::to_bytes(&asset)?,
+                bcs::to_bytes(&config.enabled)?,
+                bcs::to_bytes(&config.min_deposit)?,
+                bcs::to_bytes(&config.max_deposit)?,
+                bcs::to_bytes(&config.min_withdraw)?,
+                bcs::to_bytes(&config.max_withdraw)?,
+                bcs::to_bytes(&config.lp_exchange_rate)?,
+            ],
+        )
+    }
+
+    pub async fn upsert_supported_asset(
+        &self,
+        asset: AccountAddress,
+        config: &AssetConfig,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let efn = self.upsert_supported_asset_payload(asset, config)?;
+        self.client
+            .send_restricted_tx(efn, &[Role::ServiceAccount], opts)
+            .await
+    }
+
+    pub async fn simulate_upsert_supported_asset(
+        &self,
+        asset: AccountAddress,
+        config: &AssetConfig,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.upsert_supported_asset_payload(asset, config)?;
+
+        self.client.simulate(efn, opts).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_config() {
+        let mut config = VaultConfig {
+            enable_deposit: true,
+            enable_withdraw: true,
+            system_fee_percent: 2000,
+            fee_recipient: AccountAddress::ONE,
+            referral_percents: vec![500, 300],
+        };
+        assert!(config.validate().is_ok());
+
+        config.referral_percents = vec![5000, 5000];
+        assert!(config.validate().is_err());
+        config.referral_percents = vec![u64::MAX, 1];
+        assert!(config.validate().is_err());
+
+        config.referral_percents = vec![];
+        config.system_fee_percent = 10_001;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_asset_config() {
+        let mut config = AssetConfig {
+            enabled: true,
+            min_deposit: 100,
+            max_deposit: 0,
+            min_withdraw: 100,
+            max_withdraw: 1000,
+            lp_exchange_rate: 1,
+        };
+        assert!(config.validate().is_ok());
+
+        config.max_deposit = 10;
+        assert!(config.validate().is_err());
+
+        config.max_deposit = 0;
+        config.min_withdraw = 1001;
+        assert!(config.validate().is_err());
+    }
+}
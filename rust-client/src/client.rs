@@ -1,148 +1,723 @@
-use std::{str::FromStr, sync::Arc, u64};
+use std::{str::FromStr, sync::Arc, time::Duration, u64};
 
-use anyhow::{Result, anyhow};
 use aptos_sdk::{
     bcs,
-    move_types::account_address::AccountAddress,
+    crypto::{HashValue, ed25519::Ed25519Signature},
+    move_types::{
+        account_address::AccountAddress, identifier::Identifier, language_storage::ModuleId,
+    },
     rest_client::{
-        self,
-        aptos_api_types::{EntryFunctionId, TransactionInfo, ViewRequest},
+        self, AptosBaseUrl, PendingTransaction,
+        aptos_api_types::{
+            EntryFunctionId, Event, MoveStructTag, MoveType, Transaction, TransactionInfo,
+            ViewRequest,
+        },
     },
     transaction_builder::TransactionFactory,
     types::{
         LocalAccount,
         chain_id::{ChainId, NamedChain},
-        transaction::{EntryFunction, TransactionPayload},
+        transaction::{
+            EntryFunction, RawTransaction, RawTransactionWithData, SignedTransaction,
+            TransactionPayload,
+        },
     },
 };
+use serde_json::Value;
 use url::Url;
 
 use crate::{
-    strategy::{Aries, Strategy, StrategyOptions},
+    Error, Receipt, Result, SimulationResult, TransactionSigner, TxOptions, WalletAccounts,
+    WalletId,
+    abort::MoveAbort,
+    access_control::{AccessControl, Role},
+    admin::VaultAdmin,
+    events::MoveEvent,
+    sequence::{SequenceNumberManager, is_expired_unused, is_sequence_number_error},
+    strategy::{Aries, Hyperion, Strategy, StrategyOptions, Tapp, Thala},
     utils::{get_indexer_api_endpoint, get_rest_api_endpoint},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Client {
     pub aptos_client: rest_client::Client,
     pub chain_id: ChainId,
-    indexer_url: &'static str,
+    indexer_url: Option<Url>,
     pub contract_address: AccountAddress,
-    account: Option<LocalAccount>,
+    signer: Option<Arc<dyn TransactionSigner>>,
+    sequence: Arc<SequenceNumberManager>,
 }
 
-impl Clone for Client {
-    fn clone(&self) -> Self {
-        Client {
-            aptos_client: self.aptos_client.clone(),
-            chain_id: self.chain_id,
-            indexer_url: self.indexer_url,
-            contract_address: self.contract_address,
-            account: None,
-        }
-    }
-}
+const MAX_SEQUENCE_RESYNCS: usize = 3;
 
 impl Client {
-    pub fn new(network: String, contract_address: String) -> Self {
-        let chain = NamedChain::from_str(network.as_str()).unwrap();
-        let aptos_client =
-            rest_client::Client::new(Url::from_str(get_rest_api_endpoint(chain)).unwrap());
-
-        Client {
-            aptos_client,
-            chain_id: ChainId::new(chain.id()),
-            indexer_url: get_indexer_api_endpoint(chain),
-            contract_address: AccountAddress::from_str(contract_address.as_str()).unwrap(),
-            account: None,
-        }
+    /// Creates a client for a named network using its public endpoints
+    pub fn new(network: String, contract_address: String) -> Result<Self> {
+        ClientBuilder::new()
+            .network(network)
+            .contract_address(contract_address)
+            .build()
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
     }
 
-    pub fn connect(&mut self, private_key: String) -> &Self {
-        self.account = Some(LocalAccount::from_private_key(private_key.as_str(), 0).unwrap());
+    pub fn indexer_url(&self) -> Option<&Url> {
+        self.indexer_url.as_ref()
+    }
+
+    /// Connects a hex encoded Ed25519 private key
+    pub fn connect(&mut self, private_key: String) -> Result<&Self> {
+        let account = LocalAccount::from_private_key(private_key.as_str(), 0)
+            .map_err(|e| Error::Config(format!("invalid private key: {}", e)))?;
+
+        Ok(self.connect_signer(Arc::new(account)))
+    }
+
+    pub fn connect_signer(&mut self, signer: Arc<dyn TransactionSigner>) -> &Self {
+        self.signer = Some(signer);
         self
     }
 
+    pub fn signer_address(&self) -> Option<AccountAddress> {
+        self.signer.as_ref().map(|signer| signer.address())
+    }
+
+    pub fn access_control(&self) -> AccessControl {
+        AccessControl::new(Arc::new(self.clone()))
+    }
+
+    pub fn vault_admin(&self) -> VaultAdmin {
+        VaultAdmin::new(Arc::new(self.clone()))
+    }
+
+    pub fn wallet_accounts(&self) -> WalletAccounts {
+        WalletAccounts::new(Arc::new(self.clone()))
+    }
+
     pub fn strategy_aries(&self) -> Aries {
         Aries::new(Arc::new(self.clone()))
     }
 
-    /// Returns (total_fee, pending_fee)
-    pub async fn get_fee(&self, asset: AccountAddress) -> Result<(u64, u64)> {
+    pub fn strategy_hyperion(&self) -> Hyperion {
+        Hyperion::new(Arc::new(self.clone()))
+    }
+
+    pub fn strategy_thala(&self) -> Thala {
+        Thala::new(Arc::new(self.clone()))
+    }
+
+    pub fn strategy_tapp(&self) -> Tapp {
+        Tapp::new(Arc::new(self.clone()))
+    }
+
+    pub async fn deposit_to_strategy<S: Strategy>(
+        &self,
+        wallet_id: WalletId,
+        strategy: S,
+        asset: AccountAddress,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        strategy.deposit(wallet_id, asset, amount, opts).await
+    }
+
+    pub async fn simulate_deposit_to_strategy<S: Strategy>(
+        &self,
+        wallet_id: WalletId,
+        strategy: S,
+        asset: AccountAddress,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        strategy.simulate_deposit(wallet_id, asset, amount, opts).await
+    }
+
+    pub async fn withdraw_from_strategy<S: Strategy>(
+        &self,
+        wallet_id: WalletId,
+        strategy: S,
+        asset: AccountAddress,
+        amount: u64,
+        gas_fee: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        strategy
+            .withdraw(wallet_id, asset, amount, gas_fee, opts)
+            .await
+    }
+
+    pub async fn simulate_withdraw_from_strategy<S: Strategy>(
+        &self,
+        wallet_id: WalletId,
+        strategy: S,
+        asset: AccountAddress,
+        amount: u64,
+        gas_fee: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        strategy
+            .simulate_withdraw(wallet_id, asset, amount, gas_fee, opts)
+            .await
+    }
+
+    pub(crate) fn entry_function(
+        &self,
+        module: &str,
+        function: &str,
+        args: Vec<Vec<u8>>,
+    ) -> Result<EntryFunction> {
+        Ok(EntryFunction::new(
+            ModuleId {
+                address: self.contract_address,
+                name: Identifier::from_str(module)?,
+            },
+            Identifier::from_str(function)?,
+            vec![],
+            args,
+        ))
+    }
+
+    /// Calls a view function of the MoneyFi contract
+    pub(crate) async fn view(
+        &self,
+        module: &str,
+        function: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Value>> {
+        self.view_at(self.contract_address, module, function, arguments)
+            .await
+    }
+
+    /// Calls a view function of a module published at `address`
+    pub(crate) async fn view_at(
+        &self,
+        address: AccountAddress,
+        module: &str,
+        function: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Value>> {
+        self.view_generic_at(address, module, function, &[], arguments).await
+    }
+
+    /// Calls a generic view function, `type_arguments` are struct tags
+    pub(crate) async fn view_generic_at(
+        &self,
+        address: AccountAddress,
+        module: &str,
+        function: &str,
+        type_arguments: &[&str],
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Value>> {
+        let type_arguments = type_arguments
+            .iter()
+            .map(|tag| Ok(MoveType::Struct(MoveStructTag::from_str(tag)?)))
+            .collect::<Result<Vec<_>>>()?;
         let data = self
             .aptos_client
             .view(
                 &ViewRequest {
                     function: EntryFunctionId::from_str(
-                        format!("{}::vault::get_fee", self.contract_address).as_str(),
+                        format!("{}::{}::{}", address, module, function).as_str(),
                     )?,
-                    type_arguments: vec![],
-                    arguments: vec![serde_json::to_value(asset)?],
+                    type_arguments,
+                    arguments,
                 },
                 None,
             )
-            .await?
+            .await
+            .map_err(|e| Error::from_rest(e, self.contract_address))?
             .into_inner();
 
-        assert!(data.len() == 2);
+        Ok(data)
+    }
 
-        let total_fee = serde_json::from_value::<String>(data[0].clone())?.parse::<u64>()?;
-        let pending_fee = serde_json::from_value::<String>(data[1].clone())?.parse::<u64>()?;
+    /// Reads a resource published under `address` by the MoneyFi contract
+    pub(crate) async fn resource(
+        &self,
+        address: AccountAddress,
+        module: &str,
+        name: &str,
+    ) -> Result<Value> {
+        let resource_type = format!("{}::{}::{}", self.contract_address, module, name);
+        let resource = self
+            .aptos_client
+            .get_account_resource(address, &resource_type)
+            .await
+            .map_err(|e| Error::from_rest(e, self.contract_address))?
+            .into_inner()
+            .ok_or_else(|| Error::NotFound(format!("{} at {}", resource_type, address)))?;
 
-        Ok((total_fee, pending_fee))
+        Ok(resource.data)
     }
 
-    pub async fn deposit_to_strategy<S: Strategy>(
+    /// Signs and submits a transaction without waiting for it to be committed.
+    /// Sequence numbers are handed out locally, so many calls can be in flight at once.
+    pub async fn submit(
         &self,
-        wallet_id: Vec<u8>,
-        strategy: S,
-        asset: AccountAddress,
-        amount: u64,
-    ) -> Result<TransactionInfo> {
-        strategy.deposit(wallet_id, asset, amount).await
+        efn: EntryFunction,
+        opts: Option<TxOptions>,
+    ) -> Result<PendingTransaction> {
+        self.submit_signed_by(efn, &[], opts).await
     }
 
-    pub async fn withdraw_from_strategy<S: Strategy>(
+    /// Submits a multi-agent transaction, co-signed by `secondary_signers` in order.
+    /// `simulate_gas_margin` is ignored since simulations only cover the connected signer.
+    pub async fn submit_multi_agent(
         &self,
-        wallet_id: Vec<u8>,
-        strategy: S,
-        asset: AccountAddress,
-        amount: u64,
-        gas_fee: u64,
-    ) -> Result<TransactionInfo> {
-        strategy
-            .withdraw(wallet_id, asset, amount, gas_fee)
+        efn: EntryFunction,
+        secondary_signers: &[Arc<dyn TransactionSigner>],
+        opts: Option<TxOptions>,
+    ) -> Result<PendingTransaction> {
+        let opts = opts.map(|mut opts| {
+            opts.simulate_gas_margin = None;
+            opts
+        });
+
+        self.submit_signed_by(efn, secondary_signers, opts).await
+    }
+
+    async fn submit_signed_by(
+        &self,
+        efn: EntryFunction,
+        secondary_signers: &[Arc<dyn TransactionSigner>],
+        opts: Option<TxOptions>,
+    ) -> Result<PendingTransaction> {
+        let signer = self.signer.as_ref().ok_or(Error::NoAccount)?;
+        let opts = self.resolve_tx_options(&efn, opts).await?;
+        let mut lease = self
+            .sequence
+            .lease(&self.aptos_client, signer.address())
+            .await?;
+
+        let mut resyncs = 0;
+        loop {
+            let tx = opts
+                .apply(
+                    TransactionFactory::new(self.chain_id)
+                        .payload(TransactionPayload::EntryFunction(efn.clone()))
+                        .sequence_number(lease.sequence_number)
+                        .sender(signer.address()),
+                )
+                .build();
+
+            let signed_txn = sign_transaction(signer, tx, secondary_signers).await?;
+            match self.aptos_client.submit(&signed_txn).await {
+                Ok(pending_txn) => {
+                    lease.commit();
+                    return Ok(pending_txn.into_inner());
+                }
+                Err(e) if is_sequence_number_error(&e) && resyncs < MAX_SEQUENCE_RESYNCS => {
+                    resyncs += 1;
+                    lease.resync(&self.aptos_client).await?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Waits for a submitted transaction to be committed
+    pub async fn wait(&self, pending_txn: &PendingTransaction) -> Result<TransactionInfo> {
+        Ok(self.wait_with_events(pending_txn).await?.0)
+    }
+
+    /// Same as `wait`, also returning the events emitted by the transaction
+    pub async fn wait_with_events(
+        &self,
+        pending_txn: &PendingTransaction,
+    ) -> Result<(TransactionInfo, Vec<Event>)> {
+        let tx = match self.aptos_client.wait_for_transaction(pending_txn).await {
+            Ok(tx) => tx.into_inner(),
+            Err(e) => {
+                let err = Error::from_rest(e, self.contract_address);
+                if let Error::Transport(_) = err {
+                    self.reset_sequence_if_expired(pending_txn).await;
+                }
+                return Err(err);
+            }
+        };
+
+        let info = tx
+            .transaction_info()
+            .map_err(|e| Error::Decode(e.to_string()))?
+            .clone();
+        if !info.success {
+            return Err(Error::from_vm_status(&info.vm_status, self.contract_address));
+        }
+
+        let events = match tx {
+            Transaction::UserTransaction(txn) => txn.events,
+            _ => vec![],
+        };
+
+        Ok((info, events))
+    }
+
+    /// Goes back to the on-chain sequence number once the transaction expired without being
+    /// committed. A timeout alone keeps the local counter, later transactions of the sender
+    /// may still be in flight.
+    async fn reset_sequence_if_expired(&self, pending_txn: &PendingTransaction) {
+        let sender = *pending_txn.request.sender.inner();
+        let Ok(response) = self.aptos_client.get_account(sender).await else {
+            return;
+        };
+
+        if is_expired_unused(
+            pending_txn.request.sequence_number.0,
+            pending_txn.request.expiration_timestamp_secs.0,
+            response.inner().sequence_number,
+            response.state().timestamp_usecs / 1_000_000,
+        ) {
+            self.sequence.reset(sender).await;
+        }
+    }
+
+    /// Waits for the transaction and decodes the MoneyFi events it emitted.
+    /// A committed transaction that failed returns `Error::TransactionFailed` with its receipt.
+    pub async fn wait_for_receipt(&self, pending_txn: &PendingTransaction) -> Result<Receipt> {
+        match self.wait_with_events(pending_txn).await {
+            Ok((info, events)) => Ok(Receipt::new(&info, events, self.contract_address)),
+            Err(err @ (Error::MoveAbort(_) | Error::Execution(_))) => {
+                // the error only carries the VM status, the rest is read back from chain
+                match self.get_receipt(pending_txn.hash.into()).await {
+                    Ok(receipt) => Err(Error::TransactionFailed {
+                        receipt: Box::new(receipt),
+                        error: Box::new(err),
+                    }),
+                    Err(_) => Err(err),
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Same as `wait_for_receipt`, also decoding the `T` event the transaction must emit
+    pub(crate) async fn wait_for_event<T: MoveEvent>(
+        &self,
+        pending_txn: &PendingTransaction,
+    ) -> Result<(Receipt, T)> {
+        let receipt = self.wait_for_receipt(pending_txn).await?;
+        let event = receipt.event::<T>().map_err(|e| match e {
+            Error::NotFound(message) => Error::Decode(message),
+            e => e,
+        })?;
+
+        Ok((receipt, event))
+    }
+
+    /// Receipt of a committed transaction, successful or not
+    pub async fn get_receipt(&self, hash: HashValue) -> Result<Receipt> {
+        let tx = self
+            .aptos_client
+            .get_transaction_by_hash(hash)
             .await
+            .map_err(|e| Error::from_rest(e, self.contract_address))?
+            .into_inner();
+        let info = tx
+            .transaction_info()
+            .map_err(|e| Error::Decode(e.to_string()))?
+            .clone();
+        let events = match tx {
+            Transaction::UserTransaction(txn) => txn.events,
+            _ => vec![],
+        };
+
+        Ok(Receipt::new(&info, events, self.contract_address))
     }
 
-    async fn get_sequence_number(&self, addr: AccountAddress) -> Result<u64> {
-        let account = self.aptos_client.get_account(addr).await?.into_inner();
+    /// Decodes the abort of a committed transaction, `None` if it did not abort
+    pub async fn get_transaction_abort(&self, hash: HashValue) -> Result<Option<MoveAbort>> {
+        let tx = self
+            .aptos_client
+            .get_transaction_by_hash(hash)
+            .await
+            .map_err(|e| Error::from_rest(e, self.contract_address))?
+            .into_inner();
+        let info = tx
+            .transaction_info()
+            .map_err(|e| Error::Decode(e.to_string()))?;
+        if info.success {
+            return Ok(None);
+        }
 
-        Ok(account.sequence_number)
+        Ok(MoveAbort::from_vm_status(&info.vm_status, self.contract_address))
     }
 
-    pub(crate) async fn send_tx(&self, efn: EntryFunction) -> Result<TransactionInfo> {
-        let account = self
-            .account
-            .as_ref()
-            .ok_or_else(|| anyhow!("No account connected"))?;
+    /// Simulates a transaction from the connected signer without submitting it
+    pub async fn simulate(
+        &self,
+        efn: EntryFunction,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let mut opts = opts.unwrap_or_default();
+        opts.simulate_gas_margin = None;
+        let opts = self.resolve_tx_options(&efn, Some(opts)).await?;
 
-        let sequence_number = self.get_sequence_number(account.address()).await?;
+        self.simulate_resolved(efn, &opts).await
+    }
 
-        let tx = TransactionFactory::new(self.chain_id)
-            .payload(TransactionPayload::EntryFunction(efn))
-            .sequence_number(sequence_number)
-            .sender(account.address())
-            // .max_gas_amount(MAX_GAS_AMOUNT)
+    async fn simulate_resolved(
+        &self,
+        efn: EntryFunction,
+        opts: &TxOptions,
+    ) -> Result<SimulationResult> {
+        let signer = self.signer.as_ref().ok_or(Error::NoAccount)?;
+        // the on-chain number, a leased one may be ahead of it while transactions are in flight
+        let sequence_number = self
+            .aptos_client
+            .get_account(signer.address())
+            .await?
+            .into_inner()
+            .sequence_number;
+
+        let tx = opts
+            .apply(
+                TransactionFactory::new(self.chain_id)
+                    .payload(TransactionPayload::EntryFunction(efn))
+                    .sequence_number(sequence_number)
+                    .sender(signer.address()),
+            )
             .build();
 
-        let signed_txn = tx.sign(account.private_key(), account.public_key().clone())?;
-        let pending_txn = self.aptos_client.submit(&signed_txn).await?;
-        let tx = self
+        // the simulate endpoint rejects transactions carrying a valid signature
+        let signed_txn =
+            SignedTransaction::new(tx, signer.public_key(), Ed25519Signature::dummy_signature());
+        let txn = self
             .aptos_client
-            .wait_for_transaction(&pending_txn.inner())
-            .await?;
+            .simulate(&signed_txn)
+            .await?
+            .into_inner()
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Decode("empty simulation response".into()))?;
+
+        Ok(SimulationResult::from_user_transaction(
+            txn,
+            self.contract_address,
+        ))
+    }
+
+    /// Turns the gas policy of `opts` into a fixed gas unit price and max gas amount
+    async fn resolve_tx_options(
+        &self,
+        efn: &EntryFunction,
+        opts: Option<TxOptions>,
+    ) -> Result<TxOptions> {
+        let mut opts = opts.unwrap_or_default();
+        opts.validate().map_err(|e| Error::InvalidArgument(e.to_string()))?;
+
+        if opts.needs_gas_estimate() {
+            opts.fix_gas_unit_price(self.estimate_gas_price().await?);
+        }
+
+        if let Some(margin) = opts.simulate_gas_margin.take() {
+            let simulation = self
+                .simulate_resolved(efn.clone(), &opts)
+                .await?
+                .into_result(self.contract_address)?;
+            opts.max_gas_amount = Some(TxOptions::max_gas_with_margin(simulation.gas_used, margin));
+        }
+
+        Ok(opts)
+    }
+
+    async fn estimate_gas_price(&self) -> Result<u64> {
+        let estimation = self.aptos_client.estimate_gas_price().await?.into_inner();
+
+        Ok(estimation.gas_estimate)
+    }
+
+    pub(crate) async fn send_tx(
+        &self,
+        efn: EntryFunction,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let pending_txn = self.submit(efn, opts).await?;
+
+        self.wait_for_receipt(&pending_txn).await
+    }
+
+    /// `send_tx` for entry functions restricted to `roles`.
+    /// Fails with `Error::MissingRole` before anything is submitted.
+    pub(crate) async fn send_restricted_tx(
+        &self,
+        efn: EntryFunction,
+        roles: &[Role],
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        self.access_control().check_signer_roles(roles).await?;
+
+        self.send_tx(efn, opts).await
+    }
+
+    pub(crate) async fn send_for_event<T: MoveEvent>(
+        &self,
+        efn: EntryFunction,
+        opts: Option<TxOptions>,
+    ) -> Result<(Receipt, T)> {
+        let pending_txn = self.submit(efn, opts).await?;
+
+        self.wait_for_event(&pending_txn).await
+    }
+}
+
+async fn sign_transaction(
+    signer: &Arc<dyn TransactionSigner>,
+    txn: RawTransaction,
+    secondary_signers: &[Arc<dyn TransactionSigner>],
+) -> Result<SignedTransaction> {
+    if secondary_signers.is_empty() {
+        return signer.sign(txn).await;
+    }
+
+    let addresses = secondary_signers
+        .iter()
+        .map(|signer| signer.address())
+        .collect::<Vec<_>>();
+    let data = RawTransactionWithData::new_multi_agent(txn.clone(), addresses.clone());
+    let sender = signer.sign_multi_agent(&data).await?;
+    let mut authenticators = Vec::with_capacity(secondary_signers.len());
+    for secondary_signer in secondary_signers {
+        authenticators.push(secondary_signer.sign_multi_agent(&data).await?);
+    }
+
+    Ok(SignedTransaction::new_multi_agent(
+        txn,
+        sender,
+        addresses,
+        authenticators,
+    ))
+}
+
+/// Builds a [`Client`] against either a named network or custom endpoints.
+/// Explicit URLs and chain id take precedence over the network defaults.
+#[derive(Debug, Clone, Default)]
+pub struct ClientBuilder {
+    network: Option<String>,
+    rest_url: Option<String>,
+    indexer_url: Option<String>,
+    chain_id: Option<u8>,
+    contract_address: Option<String>,
+    api_key: Option<String>,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Named network, e.g. `mainnet`, `testnet`, `devnet` or `testing`
+    pub fn network(mut self, network: String) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    pub fn rest_url(mut self, rest_url: String) -> Self {
+        self.rest_url = Some(rest_url);
+        self
+    }
+
+    pub fn indexer_url(mut self, indexer_url: String) -> Self {
+        self.indexer_url = Some(indexer_url);
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u8) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn contract_address(mut self, contract_address: String) -> Self {
+        self.contract_address = Some(contract_address);
+        self
+    }
+
+    /// Sent as a bearer token on every REST request
+    pub fn api_key(mut self, api_key: String) -> Self {
+        self.api_key = Some(api_key);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let chain = self
+            .network
+            .as_deref()
+            .map(|network| {
+                NamedChain::from_str(network)
+                    .map_err(|_| Error::Config(format!("unknown network: {}", network)))
+            })
+            .transpose()?;
+
+        let rest_url = match (&self.rest_url, chain) {
+            (Some(url), _) => url.as_str(),
+            (None, Some(chain)) => get_rest_api_endpoint(chain).ok_or_else(|| {
+                Error::Config(format!("no default REST endpoint for {}", chain))
+            })?,
+            (None, None) => return Err(Error::Config("REST URL or network is required".into())),
+        };
+        let rest_url = Url::from_str(rest_url)
+            .map_err(|e| Error::Config(format!("invalid REST URL {}: {}", rest_url, e)))?;
+
+        let indexer_url = match (&self.indexer_url, chain) {
+            (Some(url), _) => Some(url.as_str()),
+            (None, Some(chain)) => get_indexer_api_endpoint(chain),
+            (None, None) => None,
+        }
+        .map(|url| {
+            Url::from_str(url)
+                .map_err(|e| Error::Config(format!("invalid indexer URL {}: {}", url, e)))
+        })
+        .transpose()?;
+
+        let chain_id = match (self.chain_id, chain) {
+            (Some(id), _) => ChainId::new(id),
+            (None, Some(chain)) => ChainId::new(chain.id()),
+            (None, None) => return Err(Error::Config("chain id or network is required".into())),
+        };
+
+        let contract_address = self
+            .contract_address
+            .as_deref()
+            .ok_or_else(|| Error::Config("contract address is required".into()))?;
+        let contract_address = AccountAddress::from_str(contract_address).map_err(|e| {
+            Error::Config(format!("invalid contract address {}: {}", contract_address, e))
+        })?;
+
+        let mut builder = rest_client::Client::builder(AptosBaseUrl::Custom(rest_url));
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(api_key) = &self.api_key {
+            builder = builder
+                .api_key(api_key)
+                .map_err(|e| Error::Config(format!("invalid API key: {}", e)))?;
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder
+                .header(reqwest::header::USER_AGENT.as_str(), user_agent)
+                .map_err(|e| Error::Config(format!("invalid user agent: {}", e)))?;
+        }
 
-        Ok(tx.inner().transaction_info().unwrap().clone())
+        Ok(Client {
+            aptos_client: builder.build(),
+            chain_id,
+            indexer_url,
+            contract_address,
+            signer: None,
+            sequence: Arc::new(SequenceNumberManager::default()),
+        })
     }
 }
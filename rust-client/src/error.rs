@@ -0,0 +1,134 @@
+use std::num::ParseIntError;
+
+use aptos_sdk::{bcs, move_types::account_address::AccountAddress, rest_client::error::RestError};
+use serde_json::Value;
+
+use crate::{
+    Receipt,
+    abort::{AbortReason, MoveAbort},
+    access_control::Role,
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no account connected")]
+    NoAccount,
+
+    #[error("invalid configuration: {0}")]
+    Config(String),
+
+    /// Rejected client-side before submitting, the transaction would abort on-chain
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    /// The on-chain config or state does not allow the operation, e.g. deposits disabled
+    #[error("not allowed: {0}")]
+    NotAllowed(String),
+
+    /// The signer lacks the role the contract checks for the operation
+    #[error("account {account} is missing the {role} role")]
+    MissingRole { account: AccountAddress, role: Role },
+
+    /// A wallet id that is not 32 bytes. `wallet_account.move` does not check the length,
+    /// so events and views may still carry one.
+    #[error("wallet id must be 32 bytes, got {0}")]
+    InvalidWalletId(usize),
+
+    #[error("not found: {0}")]
+    NotFound(String),
+
+    #[error("not supported: {0}")]
+    Unsupported(String),
+
+    #[error("transport error: {0}")]
+    Transport(#[from] RestError),
+
+    /// Transaction aborted in one of the Move modules
+    #[error("move abort in {0}")]
+    MoveAbort(MoveAbort),
+
+    /// Transaction failed for a reason other than a Move abort, e.g. out of gas
+    #[error("execution failed: {0}")]
+    Execution(String),
+
+    /// A committed transaction that did not succeed, `error` is the `MoveAbort` or
+    /// `Execution` error and `receipt` has its hash, version and gas used
+    #[error("transaction {hash} failed: {error}", hash = .receipt.hash)]
+    TransactionFailed {
+        receipt: Box<Receipt>,
+        error: Box<Error>,
+    },
+
+    #[error("failed to decode response: {0}")]
+    Decode(String),
+
+    /// A contract event that failed to decode, e.g. with a wallet id that is not 32 bytes.
+    /// Event streams yield it and carry on with the next event.
+    #[error("failed to decode event {typ} at version {version}: {reason}")]
+    UndecodableEvent {
+        version: u64,
+        typ: String,
+        data: Value,
+        reason: String,
+    },
+
+    #[error("failed to encode argument: {0}")]
+    Encode(#[from] bcs::Error),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl Error {
+    /// Builds an error from the VM status of a failed transaction
+    pub(crate) fn from_vm_status(vm_status: &str, contract_address: AccountAddress) -> Self {
+        match MoveAbort::from_vm_status(vm_status, contract_address) {
+            Some(abort) => Error::MoveAbort(abort),
+            None => Error::Execution(vm_status.to_string()),
+        }
+    }
+
+    /// `wait_for_transaction` reports failed executions as a REST error carrying the VM status
+    pub(crate) fn from_rest(err: RestError, contract_address: AccountAddress) -> Self {
+        let message = err.to_string();
+        if message.contains("Move abort") || message.contains("execution failed") {
+            Error::from_vm_status(&message, contract_address)
+        } else {
+            Error::Transport(err)
+        }
+    }
+
+    pub fn abort_reason(&self) -> Option<AbortReason> {
+        self.move_abort().map(|abort| abort.reason)
+    }
+
+    pub fn move_abort(&self) -> Option<&MoveAbort> {
+        match self {
+            Error::MoveAbort(abort) => Some(abort),
+            Error::TransactionFailed { error, .. } => error.move_abort(),
+            _ => None,
+        }
+    }
+
+    /// Receipt of a transaction that was committed but failed
+    pub fn receipt(&self) -> Option<&Receipt> {
+        match self {
+            Error::TransactionFailed { receipt, .. } => Some(receipt),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Decode(err.to_string())
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(err: ParseIntError) -> Self {
+        Error::Decode(err.to_string())
+    }
+}
@@ -0,0 +1,752 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aptos_sdk::{
+    move_types::account_address::AccountAddress,
+    rest_client::aptos_api_types::{MoveType, Transaction},
+};
+use futures::{Stream, stream};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    Client, Error, Result, TypeInfo, WalletId,
+    events::{MoneyFiEvent, MoveEvent, parse_event_type},
+    utils::deserialize_number_from_string,
+};
+
+const EVENTS_QUERY: &str = "query MoneyFiEvents($where: events_bool_exp!, $limit: Int!) {
+  events(
+    where: $where
+    order_by: [{ transaction_version: asc }, { event_index: asc }]
+    limit: $limit
+  ) {
+    transaction_version
+    event_index
+    type
+    data
+  }
+}";
+
+/// Position of the next event to read, `event_index` within transaction `version`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EventCursor {
+    pub version: u64,
+    pub event_index: u64,
+}
+
+impl EventCursor {
+    pub fn new(version: u64) -> Self {
+        EventCursor {
+            version,
+            event_index: 0,
+        }
+    }
+
+    fn next(&self) -> Self {
+        EventCursor {
+            version: self.version,
+            event_index: self.event_index + 1,
+        }
+    }
+}
+
+/// Persists the cursor of an event stream so it can resume after a restart
+pub trait CheckpointStore: Send + Sync {
+    fn load(&self) -> Result<Option<EventCursor>>;
+
+    fn save(&self, cursor: EventCursor) -> Result<()>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointStore {
+    cursor: Mutex<Option<EventCursor>>,
+}
+
+impl CheckpointStore for MemoryCheckpointStore {
+    fn load(&self) -> Result<Option<EventCursor>> {
+        Ok(*self.cursor.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn save(&self, cursor: EventCursor) -> Result<()> {
+        *self.cursor.lock().unwrap_or_else(|e| e.into_inner()) = Some(cursor);
+
+        Ok(())
+    }
+}
+
+/// Keeps the cursor in a JSON file, replaced through a rename so a crash never leaves it torn
+#[derive(Debug, Clone)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileCheckpointStore { path: path.into() }
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Result<Option<EventCursor>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::Other(e.into())),
+        }
+    }
+
+    fn save(&self, cursor: EventCursor) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&cursor)?).map_err(|e| Error::Other(e.into()))?;
+
+        fs::rename(&tmp_path, &self.path).map_err(|e| Error::Other(e.into()))
+    }
+}
+
+/// Selects the events of a stream. Every MoneyFi event matches an empty filter,
+/// each criterion set narrows it down.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    kinds: Vec<(&'static str, &'static str)>,
+    wallet_id: Option<WalletId>,
+    asset: Option<AccountAddress>,
+    strategy: Option<TypeInfo>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an event type, e.g. `EventFilter::new().kind::<ShareFeeEvent>()`
+    pub fn kind<T: MoveEvent>(mut self) -> Self {
+        self.kinds.push((T::MODULE, T::NAME));
+        self
+    }
+
+    /// Keeps the events keyed by this wallet id
+    pub fn wallet_id(mut self, wallet_id: WalletId) -> Self {
+        self.wallet_id = Some(wallet_id);
+        self
+    }
+
+    /// Keeps the events moving or configuring this asset
+    pub fn asset(mut self, asset: AccountAddress) -> Self {
+        self.asset = Some(asset);
+        self
+    }
+
+    /// Keeps the events keyed by this strategy type
+    pub fn strategy(mut self, strategy: TypeInfo) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    pub fn matches_type(&self, module: &str, name: &str) -> bool {
+        self.kinds.is_empty()
+            || self
+                .kinds
+                .iter()
+                .any(|(kind_module, kind_name)| *kind_module == module && *kind_name == name)
+    }
+
+    pub fn matches(&self, event: &MoneyFiEvent) -> bool {
+        self.wallet_id
+            .is_none_or(|wallet_id| event.wallet_id() == Some(wallet_id))
+            && self
+                .asset
+                .is_none_or(|asset| event.assets().contains(&asset))
+            && self
+                .strategy
+                .as_ref()
+                .is_none_or(|strategy| event.strategy() == Some(strategy))
+    }
+
+    /// `events_bool_exp` selecting the contract events from `cursor` onwards, narrowed down
+    /// to the wallet when set. The other criteria are only applied client side.
+    fn indexer_where(&self, contract_address: AccountAddress, cursor: EventCursor) -> Value {
+        let contract = contract_address.to_standard_string();
+        let type_exp = if self.kinds.is_empty() {
+            json!({ "indexed_type": { "_like": format!("{}::%", contract) } })
+        } else {
+            let types = self
+                .kinds
+                .iter()
+                .map(|(module, name)| format!("{}::{}::{}", contract, module, name))
+                .collect::<Vec<_>>();
+            json!({ "indexed_type": { "_in": types } })
+        };
+
+        let mut exps = vec![
+            type_exp,
+            json!({
+                "_or": [
+                    { "transaction_version": { "_gt": cursor.version } },
+                    {
+                        "transaction_version": { "_eq": cursor.version },
+                        "event_index": { "_gte": cursor.event_index }
+                    }
+                ]
+            }),
+        ];
+        if let Some(wallet_id) = self.wallet_id {
+            exps.push(json!({ "data": { "_contains": { "wallet_id": wallet_id.to_hex() } } }));
+        }
+
+        json!({ "_and": exps })
+    }
+}
+
+/// Where the stream reads events from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSource {
+    /// The GraphQL indexer, queried by event type
+    Indexer,
+    /// Every committed transaction through the REST API, for nodes without an indexer
+    Transactions,
+}
+
+#[derive(Clone)]
+pub struct EventStreamOptions {
+    /// Indexer events or REST transactions fetched per request
+    pub page_size: u16,
+    /// Wait once the stream caught up with the chain
+    pub poll_interval: Duration,
+    /// Defaults to the indexer when the client has an indexer URL
+    pub source: Option<EventSource>,
+    /// Resumes from the stored cursor when it is past `from_version`
+    pub checkpoint: Option<Arc<dyn CheckpointStore>>,
+}
+
+impl Default for EventStreamOptions {
+    fn default() -> Self {
+        EventStreamOptions {
+            page_size: 100,
+            poll_interval: Duration::from_secs(5),
+            source: None,
+            checkpoint: None,
+        }
+    }
+}
+
+impl EventStreamOptions {
+    pub fn page_size(mut self, page_size: u16) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn source(mut self, source: EventSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn checkpoint(mut self, checkpoint: Arc<dyn CheckpointStore>) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct IndexerResponse {
+    data: Option<IndexerEvents>,
+    #[serde(default)]
+    errors: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct IndexerEvents {
+    events: Vec<IndexerEvent>,
+}
+
+#[derive(Deserialize)]
+struct IndexerEvent {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    transaction_version: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    event_index: u64,
+    #[serde(rename = "type")]
+    typ: String,
+    data: Value,
+}
+
+struct EventPoller {
+    client: Client,
+    http: reqwest::Client,
+    filter: EventFilter,
+    options: EventStreamOptions,
+    started: bool,
+    /// Next position to fetch
+    cursor: EventCursor,
+    /// Matching events fetched but not yielded yet, `Error::UndecodableEvent` for the ones
+    /// that failed to decode
+    buffer: VecDeque<(EventCursor, Result<MoneyFiEvent>)>,
+    /// Position after the last yielded event, saved once the consumer asks for the next one
+    delivered: Option<EventCursor>,
+    saved: Option<EventCursor>,
+    backoff: bool,
+}
+
+impl EventPoller {
+    fn new(
+        client: Client,
+        filter: EventFilter,
+        from_version: u64,
+        options: EventStreamOptions,
+    ) -> Self {
+        EventPoller {
+            client,
+            http: reqwest::Client::new(),
+            filter,
+            options,
+            started: false,
+            cursor: EventCursor::new(from_version),
+            buffer: VecDeque::new(),
+            delivered: None,
+            saved: None,
+            backoff: false,
+        }
+    }
+
+    async fn next(&mut self) -> Result<(u64, MoneyFiEvent)> {
+        if !self.started {
+            if let Some(checkpoint) = self.load_checkpoint()? {
+                self.cursor = self.cursor.max(checkpoint);
+            }
+            self.started = true;
+        }
+
+        loop {
+            // saved only now, so an event is never skipped if the consumer stops before handling it
+            if let Some(delivered) = self.delivered {
+                self.save_checkpoint(delivered)?;
+                self.delivered = None;
+            }
+            if let Some((position, event)) = self.buffer.pop_front() {
+                self.delivered = Some(position.next());
+                return event.map(|event| (position.version, event));
+            }
+
+            if self.backoff {
+                tokio::time::sleep(self.options.poll_interval).await;
+            }
+            self.backoff = true;
+            let page_full = self.fetch_page().await?;
+            self.backoff = !page_full && self.buffer.is_empty();
+            if self.buffer.is_empty() {
+                self.save_checkpoint(self.cursor)?;
+            }
+        }
+    }
+
+    fn load_checkpoint(&self) -> Result<Option<EventCursor>> {
+        match &self.options.checkpoint {
+            Some(checkpoint) => checkpoint.load(),
+            None => Ok(None),
+        }
+    }
+
+    fn save_checkpoint(&mut self, cursor: EventCursor) -> Result<()> {
+        let Some(checkpoint) = &self.options.checkpoint else {
+            return Ok(());
+        };
+        if self.saved != Some(cursor) {
+            checkpoint.save(cursor)?;
+            self.saved = Some(cursor);
+        }
+
+        Ok(())
+    }
+
+    /// Buffers the matching events of one page, returns whether the page was full
+    async fn fetch_page(&mut self) -> Result<bool> {
+        let source = self
+            .options
+            .source
+            .unwrap_or(if self.client.indexer_url().is_some() {
+                EventSource::Indexer
+            } else {
+                EventSource::Transactions
+            });
+
+        match source {
+            EventSource::Indexer => self.fetch_indexer_page().await,
+            EventSource::Transactions => self.fetch_transactions_page().await,
+        }
+    }
+
+    async fn fetch_indexer_page(&mut self) -> Result<bool> {
+        let url = self
+            .client
+            .indexer_url()
+            .ok_or_else(|| Error::Config("no indexer URL configured".into()))?
+            .clone();
+        let contract_address = self.client.contract_address;
+        let request = json!({
+            "query": EVENTS_QUERY,
+            "variables": {
+                "where": self.filter.indexer_where(contract_address, self.cursor),
+                "limit": self.options.page_size,
+            },
+        });
+
+        let response = self
+            .http
+            .post(url)
+            .json(&request)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| Error::Other(e.into()))?
+            .json::<IndexerResponse>()
+            .await
+            .map_err(|e| Error::Decode(e.to_string()))?;
+        let events = match response.data {
+            Some(data) if response.errors.is_empty() => data.events,
+            _ => {
+                return Err(Error::Decode(format!(
+                    "indexer errors: {}",
+                    Value::Array(response.errors)
+                )));
+            }
+        };
+
+        let page_full = events.len() >= self.options.page_size as usize;
+        for row in events {
+            let position = EventCursor {
+                version: row.transaction_version,
+                event_index: row.event_index,
+            };
+            let kind_matches = parse_event_type(&row.typ)
+                .is_some_and(|(_, module, name)| self.filter.matches_type(module, name));
+            if kind_matches {
+                match MoneyFiEvent::from_type_str(&row.typ, &row.data, contract_address) {
+                    Ok(event) => self.push(position, event),
+                    Err(e) => self.push_undecodable(position, row.typ, row.data, e),
+                }
+            }
+            self.cursor = position.next();
+        }
+
+        Ok(page_full)
+    }
+
+    async fn fetch_transactions_page(&mut self) -> Result<bool> {
+        let aptos_client = &self.client.aptos_client;
+        let ledger_version = aptos_client
+            .get_ledger_information()
+            .await?
+            .into_inner()
+            .version;
+        if self.cursor.version > ledger_version {
+            return Ok(false);
+        }
+
+        let txns = aptos_client
+            .get_transactions(Some(self.cursor.version), Some(self.options.page_size))
+            .await?
+            .into_inner();
+        let contract_address = self.client.contract_address;
+
+        let page_full = txns.len() >= self.options.page_size as usize;
+        for txn in txns {
+            let Some(version) = txn.version() else {
+                continue;
+            };
+            if let Transaction::UserTransaction(txn) = txn {
+                for (index, event) in txn.events.iter().enumerate() {
+                    let position = EventCursor {
+                        version,
+                        event_index: index as u64,
+                    };
+                    if position < self.cursor {
+                        continue;
+                    }
+                    let kind_matches = match &event.typ {
+                        MoveType::Struct(tag) => {
+                            *tag.address.inner() == contract_address
+                                && self
+                                    .filter
+                                    .matches_type(tag.module.as_str(), tag.name.as_str())
+                        }
+                        _ => false,
+                    };
+                    if kind_matches {
+                        match MoneyFiEvent::decode(event, contract_address) {
+                            Ok(decoded) => self.push(position, decoded),
+                            Err(e) => self.push_undecodable(
+                                position,
+                                event.typ.to_string(),
+                                event.data.clone(),
+                                e,
+                            ),
+                        }
+                    }
+                    self.cursor = position.next();
+                }
+            }
+            self.cursor = EventCursor::new(version + 1);
+        }
+
+        Ok(page_full)
+    }
+
+    fn push(&mut self, position: EventCursor, event: Option<MoneyFiEvent>) {
+        if let Some(event) = event.filter(|event| self.filter.matches(event)) {
+            self.buffer.push_back((position, Ok(event)));
+        }
+    }
+
+    /// Kept whatever the wallet, asset or strategy filter, those fields can't be read
+    fn push_undecodable(&mut self, position: EventCursor, typ: String, data: Value, err: Error) {
+        self.buffer.push_back((
+            position,
+            Err(Error::UndecodableEvent {
+                version: position.version,
+                typ,
+                data,
+                reason: err.to_string(),
+            }),
+        ));
+    }
+}
+
+impl Client {
+    /// Follows the MoneyFi events from `from_version` in chain order, see `event_stream_with`
+    pub fn event_stream(
+        &self,
+        filter: EventFilter,
+        from_version: u64,
+    ) -> impl Stream<Item = Result<(u64, MoneyFiEvent)>> + Send + 'static {
+        self.event_stream_with(filter, from_version, EventStreamOptions::default())
+    }
+
+    /// Yields `(transaction version, event)` for every matching event and never ends,
+    /// polling once it caught up. A failed request yields an error and is retried on the
+    /// next poll. An event that fails to decode yields `Error::UndecodableEvent` and the
+    /// stream moves past it. With a checkpoint store the stream resumes after the last event
+    /// the consumer finished with, which is saved when the following item is requested.
+    pub fn event_stream_with(
+        &self,
+        filter: EventFilter,
+        from_version: u64,
+        options: EventStreamOptions,
+    ) -> impl Stream<Item = Result<(u64, MoneyFiEvent)>> + Send + 'static {
+        let poller = EventPoller::new(self.clone(), filter, from_version, options);
+
+        stream::unfold(poller, |mut poller| async move {
+            let item = poller.next().await;
+            Some((item, poller))
+        })
+    }
+
+    /// Reads the matching events from `from_version` up to the chain head and returns.
+    /// The checkpoint store of `options` is not used. Fails with `Error::UndecodableEvent`
+    /// on the first event that does not decode.
+    pub async fn fetch_events(
+        &self,
+        filter: EventFilter,
+        from_version: u64,
+        options: EventStreamOptions,
+    ) -> Result<Vec<(u64, MoneyFiEvent)>> {
+        let mut poller = EventPoller::new(self.clone(), filter, from_version, options);
+        let mut events = Vec::new();
+        loop {
+            let page_full = poller.fetch_page().await?;
+            for (position, event) in poller.buffer.drain(..) {
+                events.push((position.version, event?));
+            }
+            if !page_full {
+                return Ok(events);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        events::{DepositedToStrategyEvent, ShareFeeEvent, WithdrawFeeEvent},
+        utils::serve_json,
+    };
+
+    #[test]
+    fn test_filter_matches() {
+        let wallet_id = WalletId::from_address(AccountAddress::ONE);
+        let strategy = TypeInfo {
+            account_address: "0xabc".into(),
+            module_name: "strategy_aries".into(),
+            struct_name: "Strategy".into(),
+        };
+        let event = MoneyFiEvent::DepositedToStrategy(DepositedToStrategyEvent {
+            wallet_id,
+            asset: AccountAddress::from_str("0xa").unwrap(),
+            strategy: strategy.clone(),
+            amount: 100,
+            timestamp: 0,
+        });
+
+        let filter = EventFilter::new()
+            .kind::<DepositedToStrategyEvent>()
+            .kind::<ShareFeeEvent>();
+        assert!(filter.matches_type("vault", "ShareFeeEvent"));
+        assert!(!filter.matches_type("vault", "DepositedEvent"));
+        assert!(EventFilter::new().matches_type("vault", "DepositedEvent"));
+
+        let filter = filter.wallet_id(wallet_id).strategy(strategy);
+        assert!(filter.matches(&event));
+        assert!(!filter.clone().asset(AccountAddress::ONE).matches(&event));
+
+        // events without a wallet id never match a wallet filter
+        let fee = MoneyFiEvent::WithdrawFee(WithdrawFeeEvent {
+            asset: AccountAddress::from_str("0xa").unwrap(),
+            recipient: AccountAddress::ONE,
+            amount: 1,
+            timestamp: 0,
+        });
+        assert!(!filter.matches(&fee));
+    }
+
+    #[test]
+    fn test_indexer_where() {
+        let contract = AccountAddress::from_str("0xabc").unwrap();
+        let cursor = EventCursor {
+            version: 10,
+            event_index: 2,
+        };
+
+        let exp = EventFilter::new()
+            .kind::<ShareFeeEvent>()
+            .indexer_where(contract, cursor);
+        assert_eq!(
+            exp["_and"][0]["indexed_type"]["_in"][0],
+            format!("{}::vault::ShareFeeEvent", contract.to_standard_string())
+        );
+        assert_eq!(exp["_and"][1]["_or"][1]["event_index"]["_gte"], 2);
+        assert!(exp["_and"].get(2).is_none());
+
+        let wallet_id = WalletId::from_address(AccountAddress::ONE);
+        let exp = EventFilter::new()
+            .wallet_id(wallet_id)
+            .indexer_where(contract, cursor);
+        assert_eq!(
+            exp["_and"][2]["data"]["_contains"]["wallet_id"],
+            wallet_id.to_hex()
+        );
+    }
+
+    #[test]
+    fn test_file_checkpoint_store() {
+        let path = std::env::temp_dir().join(format!("moneyfi-cursor-{}.json", std::process::id()));
+        let store = FileCheckpointStore::new(&path);
+        assert_eq!(store.load().unwrap(), None);
+
+        let cursor = EventCursor {
+            version: 42,
+            event_index: 3,
+        };
+        store.save(cursor).unwrap();
+        assert_eq!(store.load().unwrap(), Some(cursor));
+        assert!(cursor < EventCursor::new(43));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_poller_skips_undecodable_and_resumes() {
+        let fee = |version: u64| {
+            json!({
+                "transaction_version": version.to_string(),
+                "event_index": "0",
+                "type": "0xabc::vault::WithdrawFeeEvent",
+                "data": {
+                    "asset": { "inner": "0xa" },
+                    "recipient": "0x1",
+                    "amount": "1",
+                    "timestamp": "0"
+                }
+            })
+        };
+        // wallet id of 2 bytes
+        let bad = json!({
+            "transaction_version": "10",
+            "event_index": "1",
+            "type": "0xabc::vault::DepositedToStrategyEvent",
+            "data": {
+                "wallet_id": "0x0102",
+                "asset": { "inner": "0xa" },
+                "strategy": {
+                    "account_address": "0xabc",
+                    "module_name": "strategy_aries",
+                    "struct_name": "Strategy"
+                },
+                "amount": "1",
+                "timestamp": "0"
+            }
+        });
+        let page = |events: Vec<Value>| json!({ "data": { "events": events } });
+        let (url, requests) = serve_json(vec![
+            page(vec![fee(10), bad]),
+            page(vec![fee(11)]),
+            page(vec![fee(12)]),
+        ])
+        .await;
+
+        let client = Client::builder()
+            .rest_url("http://127.0.0.1:1".into())
+            .indexer_url(url.to_string())
+            .chain_id(4)
+            .contract_address("0xabc".into())
+            .build()
+            .unwrap();
+        let store = Arc::new(MemoryCheckpointStore::default());
+        let options = EventStreamOptions::default()
+            .source(EventSource::Indexer)
+            .page_size(2)
+            .poll_interval(Duration::from_millis(1))
+            .checkpoint(store.clone());
+        let cursor = |version, event_index| {
+            Some(EventCursor {
+                version,
+                event_index,
+            })
+        };
+
+        let mut poller = EventPoller::new(client.clone(), EventFilter::new(), 0, options.clone());
+        assert_eq!(poller.next().await.unwrap().0, 10);
+        assert_eq!(store.load().unwrap(), None);
+        match poller.next().await {
+            Err(Error::UndecodableEvent { version, typ, .. }) => {
+                assert_eq!(version, 10);
+                assert!(typ.ends_with("DepositedToStrategyEvent"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(store.load().unwrap(), cursor(10, 1));
+        assert_eq!(poller.next().await.unwrap().0, 11);
+        assert_eq!(store.load().unwrap(), cursor(10, 2));
+        drop(poller);
+
+        // a restarted stream continues after the undecodable event
+        let mut poller = EventPoller::new(client, EventFilter::new(), 0, options);
+        assert_eq!(poller.next().await.unwrap().0, 12);
+
+        let requests = requests.await.unwrap();
+        let position = &requests[2]["variables"]["where"]["_and"][1]["_or"];
+        assert_eq!(position[0]["transaction_version"]["_gt"], 10);
+        assert_eq!(position[1]["event_index"]["_gte"], 2);
+    }
+}
@@ -1,26 +1,649 @@
 use std::str::FromStr;
 
-use aptos_sdk::types::account_config::Object;
-use serde::{Deserialize, Serialize};
+use aptos_sdk::{
+    move_types::account_address::AccountAddress,
+    rest_client::aptos_api_types::{Event, MoveType},
+};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
 
-use crate::{strategy::StrategyID, types::TypeInfo};
+use crate::{
+    OrderedMap, Result, WalletId,
+    access_control::RoleIds,
+    strategy::StrategyID,
+    types::TypeInfo,
+    utils::{
+        deserialize_bytes_from_hexstring, deserialize_number_from_string, deserialize_number_map,
+        deserialize_numbers_from_strings, deserialize_object_address,
+        deserialize_option_number_from_string, deserialize_optional_wallet_id,
+    },
+};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// An event struct declared by a MoneyFi module
+pub trait MoveEvent: DeserializeOwned {
+    const MODULE: &'static str;
+    const NAME: &'static str;
+
+    fn matches(event: &Event, contract_address: AccountAddress) -> bool {
+        match &event.typ {
+            MoveType::Struct(tag) => {
+                *tag.address.inner() == contract_address
+                    && tag.module.as_str() == Self::MODULE
+                    && tag.name.as_str() == Self::NAME
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Decodes the first event of type `T` emitted by the contract
+pub fn find_event<T: MoveEvent>(
+    events: &[Event],
+    contract_address: AccountAddress,
+) -> Result<Option<T>> {
+    events
+        .iter()
+        .find(|event| T::matches(event, contract_address))
+        .map(|event| Ok(serde_json::from_value(event.data.clone())?))
+        .transpose()
+}
+
+/// Emitted by `vault::deposit`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DepositedEvent {
+    pub sender: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub wallet_account: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    /// LP tokens minted to the sender
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for DepositedEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "DepositedEvent";
+}
+
+/// Emitted by `vault::withdraw`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawnEvent {
+    pub sender: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub wallet_account: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    /// LP tokens burned from the sender
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for WithdrawnEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "WithdrawnEvent";
+}
+
+/// Emitted by `wallet_account::register`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WalletAccountCreatedEvent {
+    pub wallet_id: WalletId,
+    pub chain_id: u8,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub wallet_account: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for WalletAccountCreatedEvent {
+    const MODULE: &'static str = "wallet_account";
+    const NAME: &'static str = "WalletAccountCreatedEvent";
+}
+
+/// Emitted by `wallet_account::config_fee`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFeeEvent {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub account: AccountAddress,
+    #[serde(deserialize_with = "deserialize_option_number_from_string")]
+    pub system_fee_percent_before: Option<u64>,
+    #[serde(deserialize_with = "deserialize_option_number_from_string")]
+    pub system_fee_percent: Option<u64>,
+    #[serde(deserialize_with = "deserialize_numbers_from_strings")]
+    pub referral_percents_before: Vec<u64>,
+    #[serde(deserialize_with = "deserialize_numbers_from_strings")]
+    pub referral_percents: Vec<u64>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for ConfigFeeEvent {
+    const MODULE: &'static str = "wallet_account";
+    const NAME: &'static str = "ConfigFeeEvent";
+}
+
+/// Emitted by `wallet_account::set_referrer`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SetReferrerEvent {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub account: AccountAddress,
+    /// `None` when the referrer was cleared
+    #[serde(deserialize_with = "deserialize_optional_wallet_id")]
+    pub referrer_wallet_id: Option<WalletId>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for SetReferrerEvent {
+    const MODULE: &'static str = "wallet_account";
+    const NAME: &'static str = "SetReferrerEvent";
+}
+
+/// Emitted by `access_control::upsert_account`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UpdateAccountEvent {
+    pub account: AccountAddress,
+    pub roles: RoleIds,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for UpdateAccountEvent {
+    const MODULE: &'static str = "access_control";
+    const NAME: &'static str = "UpdateAccountEvent";
+}
+
+/// Emitted by `access_control::remove_account`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RemoveAccountEvent {
+    pub account: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for RemoveAccountEvent {
+    const MODULE: &'static str = "access_control";
+    const NAME: &'static str = "RemoveAccountEvent";
+}
+
+/// Emitted by `vault::upsert_supported_asset`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UpsertAssetSupportedEvent {
+    pub asset_addr: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_deposit: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_deposit: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_withdraw: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_withdraw: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_exchange_rate: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for UpsertAssetSupportedEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "UpsertAssetSupportedEvent";
+}
+
+/// Emitted by `vault::configure`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigureEvent {
+    pub enable_deposit: bool,
+    pub enable_withdraw: bool,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub system_fee_percent: u64,
+    #[serde(deserialize_with = "deserialize_numbers_from_strings")]
+    pub referral_percents: Vec<u64>,
+    pub fee_recipient: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for ConfigureEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "ConfigureEvent";
+}
+
+/// Emitted by `vault::deposit_to_strategy_vault`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct DepositedToStrategyEvent {
-    wallet_id: Vec<u8>,
-    asset: Object,
-    strategy: TypeInfo,
-    amount: u64,
-    timestamp: u64,
+    pub wallet_id: WalletId,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    pub strategy: TypeInfo,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for DepositedToStrategyEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "DepositedToStrategyEvent";
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Emitted by `vault::withdraw_from_strategy_vault`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct WithdrawnFromStrategyEvent {
-    wallet_id: Vec<u8>,
-    asset: Object,
-    strategy: TypeInfo,
-    amount: u64,
-    timestamp: u64,
+    pub wallet_id: WalletId,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    pub strategy: TypeInfo,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interest_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub system_fee: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for WithdrawnFromStrategyEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "WithdrawnFromStrategyEvent";
+}
+
+/// Deprecated by `DepositedToStrategyEvent`, `strategy` is a legacy `StrategyID`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DepositToStrategyEvent {
+    pub wallet_id: WalletId,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    pub strategy: u8,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for DepositToStrategyEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "DepositToStrategyEvent";
+}
+
+/// Deprecated by `WithdrawnFromStrategyEvent`, `strategy` is a legacy `StrategyID`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawFromStrategyEvent {
+    pub wallet_id: WalletId,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    pub strategy: u8,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interest_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub system_fee: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for WithdrawFromStrategyEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "WithdrawFromStrategyEvent";
+}
+
+/// Deprecated, `strategy` is a legacy `StrategyID`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RebalanceEvent {
+    pub wallet_id: WalletId,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    pub strategy: u8,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interest_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub system_fee: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for RebalanceEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "RebalanceEvent";
+}
+
+/// Deprecated
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RebalanceStrategyEvent {
+    pub wallet_id: WalletId,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    pub strategy: TypeInfo,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interest_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub system_fee: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for RebalanceStrategyEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "RebalanceStrategyEvent";
+}
+
+/// Emitted by `vault::swap_assets`, `strategy` is a legacy `StrategyID`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SwapAssetsEvent {
+    pub wallet_id: WalletId,
+    pub strategy: u8,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub from_asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub to_asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount_in: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount_out: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_amount_in: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_amount_out: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for SwapAssetsEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "SwapAssetsEvent";
+}
+
+/// Emitted by `vault::withdraw_fee`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawFeeEvent {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    pub recipient: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for WithdrawFeeEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "WithdrawFeeEvent";
+}
+
+/// Opaque payload emitted by the vault hooks
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HookEvent {
+    #[serde(deserialize_with = "deserialize_bytes_from_hexstring")]
+    pub data: Vec<u8>,
+}
+
+impl MoveEvent for HookEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "HookEvent";
+}
+
+/// Emitted when a referrer claims its referral fees, e.g. on `vault::withdraw`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClaimReferralFeeEvent {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    pub recipient: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for ClaimReferralFeeEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "ClaimReferralFeeEvent";
+}
+
+/// Emitted when a system fee is split between the protocol and the referrers
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShareFeeEvent {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub total_fee: u64,
+    /// Referrer wallet account -> fee
+    #[serde(deserialize_with = "deserialize_number_map")]
+    pub referral_fees: OrderedMap<AccountAddress, u64>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for ShareFeeEvent {
+    const MODULE: &'static str = "vault";
+    const NAME: &'static str = "ShareFeeEvent";
+}
+
+/// Emitted by `strategy_aries::create_vault`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VaultCreatedEvent {
+    pub address: AccountAddress,
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timestamp: u64,
+}
+
+impl MoveEvent for VaultCreatedEvent {
+    const MODULE: &'static str = "strategy_aries";
+    const NAME: &'static str = "VaultCreatedEvent";
+}
+
+/// Splits a fully qualified event type, e.g. `0x..::vault::DepositedEvent`
+pub(crate) fn parse_event_type(typ: &str) -> Option<(AccountAddress, &str, &str)> {
+    let mut parts = typ.splitn(3, "::");
+    let (address, module, name) = (parts.next()?, parts.next()?, parts.next()?);
+
+    Some((AccountAddress::from_str(address).ok()?, module, name))
+}
+
+/// Any event emitted by the MoneyFi modules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyFiEvent {
+    Deposited(DepositedEvent),
+    Withdrawn(WithdrawnEvent),
+    UpsertAssetSupported(UpsertAssetSupportedEvent),
+    Configure(ConfigureEvent),
+    DepositedToStrategy(DepositedToStrategyEvent),
+    WithdrawnFromStrategy(WithdrawnFromStrategyEvent),
+    DepositToStrategy(DepositToStrategyEvent),
+    WithdrawFromStrategy(WithdrawFromStrategyEvent),
+    Rebalance(RebalanceEvent),
+    RebalanceStrategy(RebalanceStrategyEvent),
+    SwapAssets(SwapAssetsEvent),
+    WithdrawFee(WithdrawFeeEvent),
+    Hook(HookEvent),
+    ClaimReferralFee(ClaimReferralFeeEvent),
+    ShareFee(ShareFeeEvent),
+    WalletAccountCreated(WalletAccountCreatedEvent),
+    ConfigFee(ConfigFeeEvent),
+    SetReferrer(SetReferrerEvent),
+    VaultCreated(VaultCreatedEvent),
+    UpdateAccount(UpdateAccountEvent),
+    RemoveAccount(RemoveAccountEvent),
+}
+
+impl MoneyFiEvent {
+    /// Returns `None` for events of other contracts and for unknown event types
+    pub fn decode(event: &Event, contract_address: AccountAddress) -> Result<Option<Self>> {
+        match &event.typ {
+            MoveType::Struct(tag) if *tag.address.inner() == contract_address => {
+                Self::from_parts(tag.module.as_str(), tag.name.as_str(), &event.data)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Decodes every MoneyFi event of a transaction, in emission order
+    pub fn decode_all(events: &[Event], contract_address: AccountAddress) -> Result<Vec<Self>> {
+        let mut decoded = Vec::new();
+        for event in events {
+            if let Some(event) = Self::decode(event, contract_address)? {
+                decoded.push(event);
+            }
+        }
+
+        Ok(decoded)
+    }
+
+    /// Same as `decode_all`, keeping the MoneyFi events that fail to decode aside instead of
+    /// failing, e.g. for a transaction that is already committed
+    pub fn decode_lossy(
+        events: &[Event],
+        contract_address: AccountAddress,
+    ) -> (Vec<Self>, Vec<Event>) {
+        let mut decoded = Vec::new();
+        let mut undecoded = Vec::new();
+        for event in events {
+            match Self::decode(event, contract_address) {
+                Ok(Some(event)) => decoded.push(event),
+                Ok(None) => {}
+                Err(_) => undecoded.push(event.clone()),
+            }
+        }
+
+        (decoded, undecoded)
+    }
+
+    /// Decodes an event from its type string, e.g. `0x..::vault::DepositedEvent`,
+    /// as returned by the indexer
+    pub fn from_type_str(
+        typ: &str,
+        data: &Value,
+        contract_address: AccountAddress,
+    ) -> Result<Option<Self>> {
+        match parse_event_type(typ) {
+            Some((address, module, name)) if address == contract_address => {
+                Self::from_parts(module, name, data)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn from_parts(module: &str, name: &str, data: &Value) -> Result<Option<Self>> {
+        fn parse<T: MoveEvent>(data: &Value) -> Result<T> {
+            Ok(serde_json::from_value(data.clone())?)
+        }
+
+        let event = match (module, name) {
+            ("vault", "DepositedEvent") => Self::Deposited(parse(data)?),
+            ("vault", "WithdrawnEvent") => Self::Withdrawn(parse(data)?),
+            ("vault", "UpsertAssetSupportedEvent") => Self::UpsertAssetSupported(parse(data)?),
+            ("vault", "ConfigureEvent") => Self::Configure(parse(data)?),
+            ("vault", "DepositedToStrategyEvent") => Self::DepositedToStrategy(parse(data)?),
+            ("vault", "WithdrawnFromStrategyEvent") => Self::WithdrawnFromStrategy(parse(data)?),
+            ("vault", "DepositToStrategyEvent") => Self::DepositToStrategy(parse(data)?),
+            ("vault", "WithdrawFromStrategyEvent") => Self::WithdrawFromStrategy(parse(data)?),
+            ("vault", "RebalanceEvent") => Self::Rebalance(parse(data)?),
+            ("vault", "RebalanceStrategyEvent") => Self::RebalanceStrategy(parse(data)?),
+            ("vault", "SwapAssetsEvent") => Self::SwapAssets(parse(data)?),
+            ("vault", "WithdrawFeeEvent") => Self::WithdrawFee(parse(data)?),
+            ("vault", "HookEvent") => Self::Hook(parse(data)?),
+            ("vault", "ClaimReferralFeeEvent") => Self::ClaimReferralFee(parse(data)?),
+            ("vault", "ShareFeeEvent") => Self::ShareFee(parse(data)?),
+            ("wallet_account", "WalletAccountCreatedEvent") => {
+                Self::WalletAccountCreated(parse(data)?)
+            }
+            ("wallet_account", "ConfigFeeEvent") => Self::ConfigFee(parse(data)?),
+            ("wallet_account", "SetReferrerEvent") => Self::SetReferrer(parse(data)?),
+            ("strategy_aries", "VaultCreatedEvent") => Self::VaultCreated(parse(data)?),
+            ("access_control", "UpdateAccountEvent") => Self::UpdateAccount(parse(data)?),
+            ("access_control", "RemoveAccountEvent") => Self::RemoveAccount(parse(data)?),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(event))
+    }
+
+    /// Wallet the event is about, for events keyed by wallet id
+    pub fn wallet_id(&self) -> Option<WalletId> {
+        match self {
+            Self::DepositedToStrategy(e) => Some(e.wallet_id),
+            Self::WithdrawnFromStrategy(e) => Some(e.wallet_id),
+            Self::DepositToStrategy(e) => Some(e.wallet_id),
+            Self::WithdrawFromStrategy(e) => Some(e.wallet_id),
+            Self::Rebalance(e) => Some(e.wallet_id),
+            Self::RebalanceStrategy(e) => Some(e.wallet_id),
+            Self::SwapAssets(e) => Some(e.wallet_id),
+            Self::WalletAccountCreated(e) => Some(e.wallet_id),
+            _ => None,
+        }
+    }
+
+    /// Assets the event moves or configures, both sides for `SwapAssetsEvent`
+    pub fn assets(&self) -> Vec<AccountAddress> {
+        match self {
+            Self::Deposited(e) => vec![e.asset],
+            Self::Withdrawn(e) => vec![e.asset],
+            Self::UpsertAssetSupported(e) => vec![e.asset_addr],
+            Self::DepositedToStrategy(e) => vec![e.asset],
+            Self::WithdrawnFromStrategy(e) => vec![e.asset],
+            Self::DepositToStrategy(e) => vec![e.asset],
+            Self::WithdrawFromStrategy(e) => vec![e.asset],
+            Self::Rebalance(e) => vec![e.asset],
+            Self::RebalanceStrategy(e) => vec![e.asset],
+            Self::SwapAssets(e) => vec![e.from_asset, e.to_asset],
+            Self::WithdrawFee(e) => vec![e.asset],
+            Self::ClaimReferralFee(e) => vec![e.asset],
+            Self::ShareFee(e) => vec![e.asset],
+            _ => vec![],
+        }
+    }
+
+    /// Strategy type of the events keyed by `TypeInfo`, deprecated `u8` strategies are not mapped
+    pub fn strategy(&self) -> Option<&TypeInfo> {
+        match self {
+            Self::DepositedToStrategy(e) => Some(&e.strategy),
+            Self::WithdrawnFromStrategy(e) => Some(&e.strategy),
+            Self::RebalanceStrategy(e) => Some(&e.strategy),
+            _ => None,
+        }
+    }
+
+    /// Emission time in seconds, `HookEvent` has none
+    pub fn timestamp(&self) -> Option<u64> {
+        let timestamp = match self {
+            Self::Deposited(e) => e.timestamp,
+            Self::Withdrawn(e) => e.timestamp,
+            Self::UpsertAssetSupported(e) => e.timestamp,
+            Self::Configure(e) => e.timestamp,
+            Self::DepositedToStrategy(e) => e.timestamp,
+            Self::WithdrawnFromStrategy(e) => e.timestamp,
+            Self::DepositToStrategy(e) => e.timestamp,
+            Self::WithdrawFromStrategy(e) => e.timestamp,
+            Self::Rebalance(e) => e.timestamp,
+            Self::RebalanceStrategy(e) => e.timestamp,
+            Self::SwapAssets(e) => e.timestamp,
+            Self::WithdrawFee(e) => e.timestamp,
+            Self::Hook(_) => return None,
+            Self::ClaimReferralFee(e) => e.timestamp,
+            Self::ShareFee(e) => e.timestamp,
+            Self::WalletAccountCreated(e) => e.timestamp,
+            Self::ConfigFee(e) => e.timestamp,
+            Self::SetReferrer(e) => e.timestamp,
+            Self::VaultCreated(e) => e.timestamp,
+            Self::UpdateAccount(e) => e.timestamp,
+            Self::RemoveAccount(e) => e.timestamp,
+        };
+
+        Some(timestamp)
+    }
 }
 
 pub trait HasStrategyTypeInfo {
@@ -42,3 +665,73 @@ impl HasStrategyTypeInfo for WithdrawnFromStrategyEvent {
         &self.strategy
     }
 }
+
+impl HasStrategyTypeInfo for RebalanceStrategyEvent {
+    fn strategy_type_info(&self) -> &TypeInfo {
+        &self.strategy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_from_type_str() {
+        let contract = AccountAddress::from_str("0xabc").unwrap();
+        let data = serde_json::json!({
+            "wallet_id": "0x0000000000000000000000000000000000000000000000000000000000001234",
+            "asset": { "inner": "0xa" },
+            "strategy": {
+                "account_address": "0xabc",
+                "module_name": "0x73747261746567795f6172696573",
+                "struct_name": "0x5374726174656779"
+            },
+            "amount": "1000",
+            "timestamp": "1700000000"
+        });
+
+        let typ = format!("{}::vault::DepositedToStrategyEvent", contract.to_standard_string());
+        let event = MoneyFiEvent::from_type_str(&typ, &data, contract).unwrap();
+        let Some(MoneyFiEvent::DepositedToStrategy(event)) = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert_eq!(event.amount, 1000);
+        assert_eq!(event.get_strategy_id(), Some(StrategyID::AriesMarket));
+
+        // other contracts and unknown types are skipped
+        let other =
+            MoneyFiEvent::from_type_str("0x1::vault::DepositedToStrategyEvent", &data, contract);
+        assert!(other.unwrap().is_none());
+        let unknown = MoneyFiEvent::from_type_str("0xabc::vault::UnknownEvent", &data, contract);
+        assert!(unknown.unwrap().is_none());
+        // a known type with unexpected data is an error
+        let invalid = MoneyFiEvent::from_type_str("0xabc::vault::DepositedEvent", &data, contract);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_decode_share_fee() {
+        let event = MoneyFiEvent::from_type_str(
+            "0xabc::vault::ShareFeeEvent",
+            &serde_json::json!({
+                "asset": { "inner": "0xa" },
+                "total_fee": "100",
+                "referral_fees": { "entries": [{ "key": "0xb", "value": "10" }] },
+                "timestamp": "1700000000"
+            }),
+            AccountAddress::from_str("0xabc").unwrap(),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(event.timestamp(), Some(1_700_000_000));
+        let MoneyFiEvent::ShareFee(event) = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert_eq!(
+            event.referral_fees.get(&AccountAddress::from_str("0xb").unwrap()),
+            Some(&10)
+        );
+    }
+}
@@ -0,0 +1,589 @@
+//! Realized yield of a wallet per strategy, replayed from `DepositedToStrategyEvent`
+//! and `WithdrawnFromStrategyEvent`.
+//!
+//! `WithdrawnFromStrategyEvent` carries the amount returned to the wallet account net of
+//! fees, the interest net of gas and the system fee, but not the principal it closes.
+//! With interest the principal is `amount + system_fee - interest_amount`. A withdrawal
+//! without interest is a loss, a break-even or a gain up to the gas fee, which the contract
+//! does not report as interest. The ledger assumes a break-even, booking what was collected
+//! beyond the cost basis as excess, until [`Ledger::reconcile`] compares it with the
+//! on-chain `AccountAsset` totals. Deprecated `u8` strategy events are not replayed.
+
+use aptos_sdk::move_types::account_address::AccountAddress;
+
+use crate::{
+    AccountAsset, Client, Error, EventFilter, EventSource, EventStreamOptions, Result, TypeInfo,
+    WalletId,
+    events::{DepositedToStrategyEvent, MoneyFiEvent, WithdrawnFromStrategyEvent},
+};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerEntryKind {
+    Deposit,
+    Withdrawal,
+    /// Loss recognized by [`Ledger::reconcile`]
+    Loss,
+    /// Excess recognized by [`Ledger::reconcile`] in principal assumed closed
+    Gain,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    /// Version of the transaction, or of the last entry of the position for a loss
+    pub version: u64,
+    pub timestamp: u64,
+    pub strategy: TypeInfo,
+    pub asset: AccountAddress,
+    pub kind: LedgerEntryKind,
+    /// Amount sent to the strategy, or returned to the wallet account net of fees
+    pub amount: u64,
+    /// Principal closed by a withdrawal
+    pub principal: u64,
+    /// Interest net of gas, before the system fee
+    pub interest: u64,
+    pub fee: u64,
+    pub loss: u64,
+    /// Collected beyond the principal by a withdrawal without interest
+    pub excess: u64,
+    /// Principal left in the strategy after the entry
+    pub cost_basis: u64,
+}
+
+/// Totals of one asset in one strategy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyPosition {
+    pub strategy: TypeInfo,
+    pub asset: AccountAddress,
+    pub deposited: u64,
+    pub principal_withdrawn: u64,
+    /// Returned to the wallet account net of fees
+    pub collected: u64,
+    pub realized_interest: u64,
+    pub fees_paid: u64,
+    pub losses: u64,
+    /// Collected beyond the principal by withdrawals without interest, gains up to the gas
+    /// fee that are not in `realized_interest`
+    pub excess: u64,
+    pub cost_basis: u64,
+    /// Principal assumed closed by withdrawals without interest, which may hide a loss
+    pub unverified_principal: u64,
+    pub opened_at: u64,
+    pub updated_at: u64,
+    /// Cost basis integrated over time, in amount * seconds
+    principal_seconds: u128,
+}
+
+impl StrategyPosition {
+    fn new(strategy: TypeInfo, asset: AccountAddress, timestamp: u64) -> Self {
+        StrategyPosition {
+            strategy,
+            asset,
+            deposited: 0,
+            principal_withdrawn: 0,
+            collected: 0,
+            realized_interest: 0,
+            fees_paid: 0,
+            losses: 0,
+            excess: 0,
+            cost_basis: 0,
+            unverified_principal: 0,
+            opened_at: timestamp,
+            updated_at: timestamp,
+            principal_seconds: 0,
+        }
+    }
+
+    fn accrue(&mut self, timestamp: u64) {
+        let elapsed = timestamp.saturating_sub(self.updated_at);
+        self.principal_seconds += self.cost_basis as u128 * elapsed as u128;
+        self.updated_at = self.updated_at.max(timestamp);
+    }
+
+    /// Interest and excess minus fees and losses
+    pub fn net_interest(&self) -> i128 {
+        self.realized_interest as i128 + self.excess as i128
+            - self.fees_paid as i128
+            - self.losses as i128
+    }
+
+    /// Simple annualized net return over the time-weighted cost basis up to `as_of`,
+    /// `None` before any principal was held for a second
+    pub fn net_apy(&self, as_of: u64) -> Option<f64> {
+        let elapsed = as_of.saturating_sub(self.updated_at);
+        let principal_seconds = self.principal_seconds + self.cost_basis as u128 * elapsed as u128;
+        if principal_seconds == 0 {
+            return None;
+        }
+
+        Some(self.net_interest() as f64 * SECONDS_PER_YEAR / principal_seconds as f64)
+    }
+}
+
+/// Outcome of [`Ledger::reconcile`], ledger totals are taken after recognizing losses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reconciliation {
+    pub asset: AccountAddress,
+    pub ledger_distributed: u64,
+    pub account_distributed: u64,
+    pub ledger_interest: u64,
+    pub account_interest: u64,
+    pub ledger_fees: u64,
+    pub account_fees: u64,
+    pub recognized_loss: u64,
+    pub recognized_gain: u64,
+}
+
+impl Reconciliation {
+    pub fn is_balanced(&self) -> bool {
+        self.ledger_distributed == self.account_distributed
+            && self.ledger_interest == self.account_interest
+            && self.ledger_fees == self.account_fees
+    }
+}
+
+/// Time-ordered strategy entries of a wallet with per position totals
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    pub wallet_id: WalletId,
+    pub entries: Vec<LedgerEntry>,
+    positions: Vec<StrategyPosition>,
+}
+
+impl Ledger {
+    pub fn new(wallet_id: WalletId) -> Self {
+        Ledger {
+            wallet_id,
+            entries: vec![],
+            positions: vec![],
+        }
+    }
+
+    /// Replays `(version, event)` pairs in chain order
+    pub fn from_events(
+        wallet_id: WalletId,
+        events: impl IntoIterator<Item = (u64, MoneyFiEvent)>,
+    ) -> Self {
+        let mut ledger = Ledger::new(wallet_id);
+        for (version, event) in events {
+            ledger.apply(version, &event);
+        }
+
+        ledger
+    }
+
+    /// Applies one event, events of other wallets or kinds are ignored
+    pub fn apply(&mut self, version: u64, event: &MoneyFiEvent) {
+        match event {
+            MoneyFiEvent::DepositedToStrategy(event) if event.wallet_id == self.wallet_id => {
+                self.apply_deposit(version, event)
+            }
+            MoneyFiEvent::WithdrawnFromStrategy(event) if event.wallet_id == self.wallet_id => {
+                self.apply_withdrawal(version, event)
+            }
+            _ => {}
+        }
+    }
+
+    pub fn positions(&self) -> &[StrategyPosition] {
+        &self.positions
+    }
+
+    pub fn position(
+        &self,
+        strategy: &TypeInfo,
+        asset: AccountAddress,
+    ) -> Option<&StrategyPosition> {
+        self.positions
+            .iter()
+            .find(|position| position.strategy == *strategy && position.asset == asset)
+    }
+
+    /// Compares the positions of `asset` with the wallet account totals, which only holds
+    /// when the ledger was replayed from the wallet's first strategy event.
+    /// Principal the ledger still counts in strategies beyond `distributed_amount` was lost
+    /// in withdrawals without interest, it is recognized as a loss of those positions
+    /// in proportion to their unverified principal. Principal missing from the ledger was a
+    /// gain of those withdrawals, it is moved back to the cost basis as excess.
+    pub fn reconcile(&mut self, asset: AccountAddress, account: &AccountAsset) -> Reconciliation {
+        let ledger_distributed = self.sum(asset, |position| position.cost_basis);
+        let unverified = self.sum(asset, |position| position.unverified_principal);
+        let shortfall = ledger_distributed
+            .saturating_sub(account.distributed_amount)
+            .min(unverified);
+        let surplus = account
+            .distributed_amount
+            .saturating_sub(ledger_distributed)
+            .min(unverified);
+
+        let recognized_loss = self.recognize(asset, LedgerEntryKind::Loss, shortfall, unverified);
+        let recognized_gain = self.recognize(asset, LedgerEntryKind::Gain, surplus, unverified);
+
+        Reconciliation {
+            asset,
+            ledger_distributed: self.sum(asset, |position| position.cost_basis),
+            account_distributed: account.distributed_amount,
+            ledger_interest: self.sum(asset, |position| position.realized_interest),
+            account_interest: account.interest_amount,
+            ledger_fees: self.sum(asset, |position| position.fees_paid),
+            account_fees: account.interest_share_amount,
+            recognized_loss,
+            recognized_gain,
+        }
+    }
+
+    /// Splits `total` over the positions of `asset` in proportion to their unverified
+    /// principal, as a loss or as excess, and returns the amount recognized
+    fn recognize(
+        &mut self,
+        asset: AccountAddress,
+        kind: LedgerEntryKind,
+        total: u64,
+        unverified: u64,
+    ) -> u64 {
+        if total == 0 {
+            return 0;
+        }
+
+        let mut recognized = 0;
+        let last = self
+            .positions
+            .iter()
+            .rposition(|position| position.asset == asset && position.unverified_principal > 0);
+        for (index, position) in self.positions.iter_mut().enumerate() {
+            if position.asset != asset || position.unverified_principal == 0 {
+                continue;
+            }
+            // the last position takes the rounding remainder
+            let amount = if Some(index) == last {
+                total - recognized
+            } else {
+                (total as u128 * position.unverified_principal as u128 / unverified as u128) as u64
+            };
+            let amount = amount.min(position.unverified_principal);
+            if amount == 0 {
+                continue;
+            }
+
+            let (principal, loss, excess) = match kind {
+                LedgerEntryKind::Gain => {
+                    position.excess += amount;
+                    position.principal_withdrawn -= amount;
+                    position.cost_basis += amount;
+                    (0, 0, amount)
+                }
+                _ => {
+                    position.losses += amount;
+                    position.cost_basis = position.cost_basis.saturating_sub(amount);
+                    (amount, amount, 0)
+                }
+            };
+            position.unverified_principal -= amount;
+            recognized += amount;
+
+            let version = self
+                .entries
+                .iter()
+                .rev()
+                .find(|entry| entry.strategy == position.strategy && entry.asset == asset)
+                .map_or(0, |entry| entry.version);
+            self.entries.push(LedgerEntry {
+                version,
+                timestamp: position.updated_at,
+                strategy: position.strategy.clone(),
+                asset,
+                kind,
+                amount: 0,
+                principal,
+                interest: 0,
+                fee: 0,
+                loss,
+                excess,
+                cost_basis: position.cost_basis,
+            });
+        }
+        self.entries.sort_by_key(|entry| entry.version);
+
+        recognized
+    }
+
+    fn sum(&self, asset: AccountAddress, f: impl Fn(&StrategyPosition) -> u64) -> u64 {
+        self.positions
+            .iter()
+            .filter(|position| position.asset == asset)
+            .map(f)
+            .sum()
+    }
+
+    fn position_mut(
+        &mut self,
+        strategy: &TypeInfo,
+        asset: AccountAddress,
+        timestamp: u64,
+    ) -> &mut StrategyPosition {
+        let index = match self
+            .positions
+            .iter()
+            .position(|position| position.strategy == *strategy && position.asset == asset)
+        {
+            Some(index) => index,
+            None => {
+                self.positions
+                    .push(StrategyPosition::new(strategy.clone(), asset, timestamp));
+                self.positions.len() - 1
+            }
+        };
+
+        let position = &mut self.positions[index];
+        position.accrue(timestamp);
+        position
+    }
+
+    fn apply_deposit(&mut self, version: u64, event: &DepositedToStrategyEvent) {
+        let position = self.position_mut(&event.strategy, event.asset, event.timestamp);
+        position.deposited += event.amount;
+        position.cost_basis += event.amount;
+        let cost_basis = position.cost_basis;
+
+        self.entries.push(LedgerEntry {
+            version,
+            timestamp: event.timestamp,
+            strategy: event.strategy.clone(),
+            asset: event.asset,
+            kind: LedgerEntryKind::Deposit,
+            amount: event.amount,
+            principal: event.amount,
+            interest: 0,
+            fee: 0,
+            loss: 0,
+            excess: 0,
+            cost_basis,
+        });
+    }
+
+    fn apply_withdrawal(&mut self, version: u64, event: &WithdrawnFromStrategyEvent) {
+        let position = self.position_mut(&event.strategy, event.asset, event.timestamp);
+        let (principal, excess) = if event.interest_amount > 0 {
+            let principal = (event.amount + event.system_fee).saturating_sub(event.interest_amount);
+            (principal, 0)
+        } else {
+            // a gain up to the gas fee is not reported as interest, anything collected beyond
+            // the cost basis is one
+            let principal = event.amount.min(position.cost_basis);
+            position.unverified_principal += principal;
+            (principal, event.amount - principal)
+        };
+
+        position.principal_withdrawn += principal;
+        position.excess += excess;
+        position.collected += event.amount;
+        position.realized_interest += event.interest_amount;
+        position.fees_paid += event.system_fee;
+        position.cost_basis = position.cost_basis.saturating_sub(principal);
+        let cost_basis = position.cost_basis;
+
+        self.entries.push(LedgerEntry {
+            version,
+            timestamp: event.timestamp,
+            strategy: event.strategy.clone(),
+            asset: event.asset,
+            kind: LedgerEntryKind::Withdrawal,
+            amount: event.amount,
+            principal,
+            interest: event.interest_amount,
+            fee: event.system_fee,
+            loss: 0,
+            excess,
+            cost_basis,
+        });
+    }
+}
+
+impl Client {
+    /// Replays the strategy events of the wallet from `from_version`, or from genesis
+    /// through the indexer. Without an indexer every transaction is read over REST, so
+    /// `from_version` is required, e.g. the version that created the wallet account.
+    pub async fn get_wallet_ledger(
+        &self,
+        wallet_id: WalletId,
+        from_version: Option<u64>,
+    ) -> Result<Ledger> {
+        let (source, from_version) = match (self.indexer_url(), from_version) {
+            (Some(_), from_version) => (EventSource::Indexer, from_version.unwrap_or_default()),
+            (None, Some(from_version)) => (EventSource::Transactions, from_version),
+            (None, None) => {
+                return Err(Error::Config(
+                    "a wallet ledger without an indexer needs a from_version".into(),
+                ));
+            }
+        };
+        let filter = EventFilter::new()
+            .kind::<DepositedToStrategyEvent>()
+            .kind::<WithdrawnFromStrategyEvent>()
+            .wallet_id(wallet_id);
+        let events = self
+            .fetch_events(
+                filter,
+                from_version,
+                EventStreamOptions::default().source(source),
+            )
+            .await?;
+
+        Ok(Ledger::from_events(wallet_id, events))
+    }
+
+    /// Same as `get_wallet_ledger`, reconciled against every asset of the wallet account.
+    /// `from_version` must precede the first strategy event of the wallet.
+    pub async fn get_reconciled_wallet_ledger(
+        &self,
+        wallet_id: WalletId,
+        from_version: Option<u64>,
+    ) -> Result<(Ledger, Vec<Reconciliation>)> {
+        let mut ledger = self.get_wallet_ledger(wallet_id, from_version).await?;
+        let assets = self
+            .wallet_accounts()
+            .get_wallet_account_assets(wallet_id)
+            .await?;
+        let reconciliations = assets
+            .iter()
+            .map(|(asset, account)| ledger.reconcile(*asset, account))
+            .collect();
+
+        Ok((ledger, reconciliations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strategy() -> TypeInfo {
+        TypeInfo {
+            account_address: "0xabc".into(),
+            module_name: "strategy_aries".into(),
+            struct_name: "Strategy".into(),
+        }
+    }
+
+    fn deposit(wallet_id: WalletId, amount: u64, timestamp: u64) -> MoneyFiEvent {
+        MoneyFiEvent::DepositedToStrategy(DepositedToStrategyEvent {
+            wallet_id,
+            asset: AccountAddress::ONE,
+            strategy: strategy(),
+            amount,
+            timestamp,
+        })
+    }
+
+    fn withdrawal(
+        wallet_id: WalletId,
+        amount: u64,
+        interest_amount: u64,
+        system_fee: u64,
+        timestamp: u64,
+    ) -> MoneyFiEvent {
+        MoneyFiEvent::WithdrawnFromStrategy(WithdrawnFromStrategyEvent {
+            wallet_id,
+            asset: AccountAddress::ONE,
+            strategy: strategy(),
+            amount,
+            interest_amount,
+            system_fee,
+            timestamp,
+        })
+    }
+
+    #[test]
+    fn test_replay_with_interest() {
+        let wallet_id = WalletId::from_address(AccountAddress::ONE);
+        let other = WalletId::from_address(AccountAddress::TWO);
+        let year = SECONDS_PER_YEAR as u64;
+        let ledger = Ledger::from_events(
+            wallet_id,
+            vec![
+                (1, deposit(wallet_id, 1000, 0)),
+                (2, deposit(other, 500, 0)),
+                // 1000 principal + 100 interest - 20 fee
+                (3, withdrawal(wallet_id, 1080, 100, 20, year)),
+            ],
+        );
+
+        assert_eq!(ledger.entries.len(), 2);
+        assert_eq!(ledger.entries[1].principal, 1000);
+        let position = ledger.position(&strategy(), AccountAddress::ONE).unwrap();
+        assert_eq!(position.cost_basis, 0);
+        assert_eq!(position.net_interest(), 80);
+        assert_eq!(position.net_apy(year), Some(0.08));
+    }
+
+    #[test]
+    fn test_reconcile_recognizes_loss() {
+        let wallet_id = WalletId::from_address(AccountAddress::ONE);
+        let mut ledger = Ledger::from_events(
+            wallet_id,
+            vec![
+                (1, deposit(wallet_id, 1000, 0)),
+                // the strategy returned 900 out of the 1000 deposited
+                (2, withdrawal(wallet_id, 900, 0, 0, 10)),
+            ],
+        );
+        assert_eq!(ledger.positions()[0].cost_basis, 100);
+
+        let account = AccountAsset {
+            distributed_amount: 0,
+            ..Default::default()
+        };
+        let reconciliation = ledger.reconcile(AccountAddress::ONE, &account);
+        assert_eq!(reconciliation.recognized_loss, 100);
+        assert!(reconciliation.is_balanced());
+        assert_eq!(ledger.positions()[0].net_interest(), -100);
+        assert_eq!(ledger.entries.last().unwrap().kind, LedgerEntryKind::Loss);
+
+        // reconciling again is a no-op
+        let reconciliation = ledger.reconcile(AccountAddress::ONE, &account);
+        assert_eq!(reconciliation.recognized_loss, 0);
+    }
+
+    #[test]
+    fn test_withdrawal_excess() {
+        let wallet_id = WalletId::from_address(AccountAddress::ONE);
+        // gains up to the gas fee come back without interest
+        let ledger = Ledger::from_events(
+            wallet_id,
+            vec![
+                (1, deposit(wallet_id, 1000, 0)),
+                (2, withdrawal(wallet_id, 1003, 0, 0, 10)),
+            ],
+        );
+        let position = &ledger.positions()[0];
+        assert_eq!(ledger.entries[1].principal, 1000);
+        assert_eq!(ledger.entries[1].excess, 3);
+        assert_eq!(position.cost_basis, 0);
+        assert_eq!(position.net_interest(), 3);
+
+        // a partial withdrawal of 500 principal with a gain of 2
+        let mut ledger = Ledger::from_events(
+            wallet_id,
+            vec![
+                (1, deposit(wallet_id, 1000, 0)),
+                (2, withdrawal(wallet_id, 502, 0, 0, 10)),
+            ],
+        );
+        assert_eq!(ledger.positions()[0].cost_basis, 498);
+
+        let account = AccountAsset {
+            distributed_amount: 500,
+            ..Default::default()
+        };
+        let reconciliation = ledger.reconcile(AccountAddress::ONE, &account);
+        assert_eq!(reconciliation.recognized_gain, 2);
+        assert_eq!(reconciliation.recognized_loss, 0);
+        assert!(reconciliation.is_balanced());
+        let position = &ledger.positions()[0];
+        assert_eq!(position.cost_basis, 500);
+        assert_eq!(position.principal_withdrawn, 500);
+        assert_eq!(position.net_interest(), 2);
+        assert_eq!(ledger.entries.last().unwrap().kind, LedgerEntryKind::Gain);
+    }
+}
@@ -1,8 +1,28 @@
+pub mod abort;
+pub mod access_control;
+pub mod admin;
 mod client;
+mod error;
 pub mod events;
+mod event_stream;
+pub mod ledger;
+mod portfolio;
+mod signer;
+mod sequence;
 pub mod strategy;
+mod transaction;
 mod types;
 mod utils;
+mod vault;
+mod wallet_account;
 mod aptos_client;
 
 pub use client::*;
+pub use error::*;
+pub use event_stream::*;
+pub use portfolio::*;
+pub use signer::*;
+pub use transaction::*;
+pub use types::*;
+pub use vault::*;
+pub use wallet_account::*;
@@ -0,0 +1,327 @@
+use aptos_sdk::move_types::account_address::AccountAddress;
+use serde::Serialize;
+
+use crate::{
+    Client, Result, WalletId,
+    strategy::{DexStrategy, Strategy, StrategyID},
+};
+
+/// Value of one asset in one strategy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PositionValuation {
+    pub asset: AccountAddress,
+    /// Held by the strategy, not deployed yet
+    pub pending_amount: u64,
+    pub deposited_amount: u64,
+    /// Estimated amount returned by a full withdrawal, before fees
+    pub withdrawable_amount: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StrategyValuation {
+    pub strategy: StrategyID,
+    /// Aries vault of the positions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vault_name: Option<String>,
+    pub positions: Vec<PositionValuation>,
+    /// Interest reported by `get_profit`, DEX strategies only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit: Option<u64>,
+}
+
+/// Totals of one asset across the wallet account and every strategy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AssetValuation {
+    pub asset: AccountAddress,
+    /// `AccountAsset::current_amount`, held by the wallet account
+    pub idle_amount: u64,
+    pub deposited_amount: u64,
+    pub withdrawable_amount: u64,
+    /// Idle plus withdrawable amount
+    pub total_amount: u64,
+}
+
+/// Strategy left out of a snapshot because one of its views failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StrategyValuationError {
+    pub strategy: StrategyID,
+    /// Aries vault, `None` when the vaults could not be listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vault_name: Option<String>,
+    pub error: String,
+}
+
+/// Current value of a wallet per strategy and per asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PortfolioSnapshot {
+    pub wallet_id: WalletId,
+    /// Ledger version read before the views, which are not taken at a single version
+    pub ledger_version: u64,
+    /// Ledger timestamp in seconds
+    pub timestamp: u64,
+    pub strategies: Vec<StrategyValuation>,
+    /// Asset totals of the wallet account and the valued strategies only
+    pub assets: Vec<AssetValuation>,
+    pub errors: Vec<StrategyValuationError>,
+}
+
+impl PortfolioSnapshot {
+    /// Builds the per asset totals from the idle balances and the strategy positions
+    pub fn new(
+        wallet_id: WalletId,
+        ledger_version: u64,
+        timestamp: u64,
+        idle: impl IntoIterator<Item = (AccountAddress, u64)>,
+        strategies: Vec<StrategyValuation>,
+    ) -> Self {
+        let mut assets: Vec<AssetValuation> = vec![];
+        let mut valuation = |asset: AccountAddress| -> usize {
+            match assets.iter().position(|valuation| valuation.asset == asset) {
+                Some(index) => index,
+                None => {
+                    assets.push(AssetValuation {
+                        asset,
+                        ..Default::default()
+                    });
+                    assets.len() - 1
+                }
+            }
+        };
+
+        let mut totals = vec![];
+        for (asset, amount) in idle {
+            totals.push((valuation(asset), amount, 0, 0));
+        }
+        for position in strategies.iter().flat_map(|strategy| &strategy.positions) {
+            totals.push((
+                valuation(position.asset),
+                0,
+                position.deposited_amount,
+                position.withdrawable_amount,
+            ));
+        }
+        for (index, idle_amount, deposited_amount, withdrawable_amount) in totals {
+            let asset = &mut assets[index];
+            asset.idle_amount += idle_amount;
+            asset.deposited_amount += deposited_amount;
+            asset.withdrawable_amount += withdrawable_amount;
+            asset.total_amount += idle_amount + withdrawable_amount;
+        }
+
+        PortfolioSnapshot {
+            wallet_id,
+            ledger_version,
+            timestamp,
+            strategies,
+            assets,
+            errors: vec![],
+        }
+    }
+
+    pub fn asset(&self, asset: AccountAddress) -> Option<&AssetValuation> {
+        self.assets
+            .iter()
+            .find(|valuation| valuation.asset == asset)
+    }
+}
+
+impl Client {
+    /// Values the wallet account balances and the positions of every strategy and Aries vault.
+    /// A strategy whose views fail is reported in `errors` instead of failing the snapshot.
+    pub async fn get_portfolio(&self, wallet_id: WalletId) -> Result<PortfolioSnapshot> {
+        let ledger = self
+            .aptos_client
+            .get_ledger_information()
+            .await?
+            .into_inner();
+
+        let idle = self
+            .wallet_accounts()
+            .get_wallet_account_assets(wallet_id)
+            .await?
+            .iter()
+            .map(|(asset, account)| (*asset, account.current_amount))
+            .collect::<Vec<_>>();
+
+        let mut strategies = vec![];
+        let mut errors = vec![];
+        match self.strategy_aries().get_vaults().await {
+            Ok(vaults) => {
+                for (_, vault_name) in vaults {
+                    match self.get_aries_valuation(wallet_id, &vault_name).await {
+                        Ok(valuation) => strategies.extend(valuation),
+                        Err(e) => errors.push(StrategyValuationError {
+                            strategy: StrategyID::AriesMarket,
+                            vault_name: Some(vault_name),
+                            error: e.to_string(),
+                        }),
+                    }
+                }
+            }
+            Err(e) => errors.push(StrategyValuationError {
+                strategy: StrategyID::AriesMarket,
+                vault_name: None,
+                error: e.to_string(),
+            }),
+        }
+
+        let dex_strategies: Vec<Box<dyn DexStrategy>> = vec![
+            Box::new(self.strategy_hyperion()),
+            Box::new(self.strategy_thala()),
+            Box::new(self.strategy_tapp()),
+        ];
+        for strategy in &dex_strategies {
+            match get_dex_valuation(strategy.as_ref(), wallet_id).await {
+                Ok(valuation) => strategies.extend(valuation),
+                Err(e) => errors.push(StrategyValuationError {
+                    strategy: strategy.strategy_id(),
+                    vault_name: None,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        Ok(PortfolioSnapshot {
+            errors,
+            ..PortfolioSnapshot::new(
+                wallet_id,
+                ledger.version,
+                ledger.timestamp_usecs / 1_000_000,
+                idle,
+                strategies,
+            )
+        })
+    }
+
+    async fn get_aries_valuation(
+        &self,
+        wallet_id: WalletId,
+        vault_name: &str,
+    ) -> Result<Option<StrategyValuation>> {
+        let mut aries = self.strategy_aries();
+        aries.with_vault_name(vault_name.to_string());
+
+        // the asset argument is ignored, the vault has a single asset
+        let state = aries
+            .get_account_state(wallet_id, AccountAddress::ZERO)
+            .await?;
+        if state.deposited_amount == 0
+            && state.pending_amount == 0
+            && state.est_withdrawable_amount == 0
+        {
+            return Ok(None);
+        }
+        let (_, vault) = aries.get_vault().await?;
+
+        Ok(Some(StrategyValuation {
+            strategy: StrategyID::AriesMarket,
+            vault_name: Some(vault_name.to_string()),
+            positions: vec![PositionValuation {
+                asset: vault.asset,
+                pending_amount: state.pending_amount,
+                deposited_amount: state.deposited_amount,
+                withdrawable_amount: state.est_withdrawable_amount,
+            }],
+            profit: None,
+        }))
+    }
+}
+
+async fn get_dex_valuation(
+    strategy: &dyn DexStrategy,
+    wallet_id: WalletId,
+) -> Result<Option<StrategyValuation>> {
+    let allocation = strategy.get_user_asset_allocation(wallet_id).await?;
+    if allocation.is_empty() {
+        return Ok(None);
+    }
+
+    let mut positions = vec![];
+    for asset in allocation_assets(&allocation) {
+        let state = strategy.get_account_state(wallet_id, asset).await?;
+        positions.push(PositionValuation {
+            asset,
+            pending_amount: state.pending_amount,
+            deposited_amount: state.deposited_amount,
+            withdrawable_amount: state.est_withdrawable_amount,
+        });
+    }
+
+    Ok(Some(StrategyValuation {
+        strategy: strategy.strategy_id(),
+        vault_name: None,
+        positions,
+        profit: Some(strategy.get_profit(wallet_id).await?),
+    }))
+}
+
+/// Distinct assets of an allocation, positions in several pools repeat their tokens
+fn allocation_assets(allocation: &[(AccountAddress, u64)]) -> Vec<AccountAddress> {
+    let mut assets = allocation
+        .iter()
+        .map(|(asset, _)| *asset)
+        .collect::<Vec<_>>();
+    assets.sort_unstable();
+    assets.dedup();
+
+    assets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_totals() {
+        let usdc = AccountAddress::from_hex_literal("0xa").unwrap();
+        let usdt = AccountAddress::from_hex_literal("0xb").unwrap();
+        let position = |asset, deposited_amount, withdrawable_amount| PositionValuation {
+            asset,
+            pending_amount: 0,
+            deposited_amount,
+            withdrawable_amount,
+        };
+        let snapshot = PortfolioSnapshot::new(
+            WalletId::from_address(AccountAddress::ONE),
+            1,
+            0,
+            vec![(usdc, 50)],
+            vec![
+                StrategyValuation {
+                    strategy: StrategyID::AriesMarket,
+                    vault_name: Some("USDCVault".into()),
+                    positions: vec![position(usdc, 100, 110)],
+                    profit: None,
+                },
+                StrategyValuation {
+                    strategy: StrategyID::Hyperion,
+                    vault_name: None,
+                    positions: vec![position(usdc, 200, 190), position(usdt, 0, 15)],
+                    profit: Some(5),
+                },
+            ],
+        );
+
+        let usdc_total = snapshot.asset(usdc).unwrap();
+        assert_eq!(usdc_total.idle_amount, 50);
+        assert_eq!(usdc_total.deposited_amount, 300);
+        assert_eq!(usdc_total.total_amount, 350);
+        assert_eq!(snapshot.asset(usdt).unwrap().total_amount, 15);
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert!(json["strategies"][1].get("vault_name").is_none());
+        assert_eq!(json["assets"][0]["total_amount"], 350);
+        assert_eq!(json["errors"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_allocation_assets() {
+        let usdc = AccountAddress::from_hex_literal("0xa").unwrap();
+        let usdt = AccountAddress::from_hex_literal("0xb").unwrap();
+        let apt = AccountAddress::from_hex_literal("0xc").unwrap();
+
+        // USDC/USDT and APT/USDC pools
+        let allocation = vec![(usdc, 10), (usdt, 20), (apt, 5), (usdc, 30)];
+        assert_eq!(allocation_assets(&allocation), vec![usdc, usdt, apt]);
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex as StdMutex},
+};
+
+use aptos_sdk::{move_types::account_address::AccountAddress, rest_client};
+use async_trait::async_trait;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::Result;
+
+/// Reads the on-chain sequence number of an account
+#[async_trait]
+pub(crate) trait SequenceNumberSource: Send + Sync {
+    async fn sequence_number(&self, address: AccountAddress) -> Result<u64>;
+}
+
+#[async_trait]
+impl SequenceNumberSource for rest_client::Client {
+    async fn sequence_number(&self, address: AccountAddress) -> Result<u64> {
+        let account = self.get_account(address).await?.into_inner();
+
+        Ok(account.sequence_number)
+    }
+}
+
+/// Hands out sequence numbers locally so one signer can have many transactions in flight.
+/// Shared by all clones of a `Client`, each account has its own lock.
+#[derive(Debug, Default)]
+pub(crate) struct SequenceNumberManager {
+    accounts: StdMutex<HashMap<AccountAddress, Arc<Mutex<Option<u64>>>>>,
+}
+
+/// Reservation of the next sequence number of an account.
+/// Submissions of the account are serialized while it is alive; the number is consumed
+/// only on `commit`.
+pub(crate) struct SequenceNumberLease {
+    guard: OwnedMutexGuard<Option<u64>>,
+    address: AccountAddress,
+    pub sequence_number: u64,
+}
+
+impl SequenceNumberManager {
+    fn account(&self, address: AccountAddress) -> Arc<Mutex<Option<u64>>> {
+        let mut accounts = self
+            .accounts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        accounts.entry(address).or_default().clone()
+    }
+
+    pub async fn lease(
+        &self,
+        source: &dyn SequenceNumberSource,
+        address: AccountAddress,
+    ) -> Result<SequenceNumberLease> {
+        let mut guard = self.account(address).lock_owned().await;
+
+        let sequence_number = match *guard {
+            Some(n) => n,
+            None => {
+                let n = source.sequence_number(address).await?;
+                *guard = Some(n);
+                n
+            }
+        };
+
+        Ok(SequenceNumberLease {
+            guard,
+            address,
+            sequence_number,
+        })
+    }
+
+    /// Forgets the local counter, the next lease fetches it from chain again
+    pub async fn reset(&self, address: AccountAddress) {
+        *self.account(address).lock().await = None;
+    }
+}
+
+impl SequenceNumberLease {
+    /// Marks the leased number as used by a submitted transaction
+    pub fn commit(mut self) {
+        *self.guard = Some(self.sequence_number + 1);
+    }
+
+    /// Refetches the on-chain sequence number, e.g. after `SEQUENCE_NUMBER_TOO_OLD`
+    pub async fn resync(&mut self, source: &dyn SequenceNumberSource) -> Result<()> {
+        self.sequence_number = source.sequence_number(self.address).await?;
+        *self.guard = Some(self.sequence_number);
+
+        Ok(())
+    }
+}
+
+/// Whether a transaction can no longer be committed and its sequence number is unused,
+/// the only case where the local counter may go back to the on-chain one
+pub(crate) fn is_expired_unused(
+    sequence_number: u64,
+    expiration_timestamp_secs: u64,
+    account_sequence_number: u64,
+    ledger_timestamp_secs: u64,
+) -> bool {
+    account_sequence_number <= sequence_number && ledger_timestamp_secs > expiration_timestamp_secs
+}
+
+/// Whether a submission failed because the local sequence number is out of sync
+pub(crate) fn is_sequence_number_error(err: &rest_client::error::RestError) -> bool {
+    let message = err.to_string();
+    message.contains("SEQUENCE_NUMBER_TOO_OLD") || message.contains("SEQUENCE_NUMBER_TOO_NEW")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeSource {
+        sequence_number: AtomicU64,
+        fetches: AtomicU64,
+    }
+
+    #[async_trait]
+    impl SequenceNumberSource for FakeSource {
+        async fn sequence_number(&self, _address: AccountAddress) -> Result<u64> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(self.sequence_number.load(Ordering::SeqCst))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lease_commit() {
+        let manager = SequenceNumberManager::default();
+        let source = FakeSource::default();
+        source.sequence_number.store(5, Ordering::SeqCst);
+
+        let lease = manager.lease(&source, AccountAddress::ONE).await.unwrap();
+        assert_eq!(lease.sequence_number, 5);
+        lease.commit();
+
+        let lease = manager.lease(&source, AccountAddress::ONE).await.unwrap();
+        assert_eq!(lease.sequence_number, 6);
+        // dropped without a submission, the number is leased again
+        drop(lease);
+        let lease = manager.lease(&source, AccountAddress::ONE).await.unwrap();
+        assert_eq!(lease.sequence_number, 6);
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_lease_resync_and_reset() {
+        let manager = SequenceNumberManager::default();
+        let source = FakeSource::default();
+
+        manager
+            .lease(&source, AccountAddress::ONE)
+            .await
+            .unwrap()
+            .commit();
+
+        source.sequence_number.store(9, Ordering::SeqCst);
+        let mut lease = manager.lease(&source, AccountAddress::ONE).await.unwrap();
+        assert_eq!(lease.sequence_number, 1);
+        lease.resync(&source).await.unwrap();
+        assert_eq!(lease.sequence_number, 9);
+        lease.commit();
+        let lease = manager.lease(&source, AccountAddress::ONE).await.unwrap();
+        assert_eq!(lease.sequence_number, 10);
+        drop(lease);
+
+        manager.reset(AccountAddress::ONE).await;
+        let lease = manager.lease(&source, AccountAddress::ONE).await.unwrap();
+        assert_eq!(lease.sequence_number, 9);
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_lease_per_account() {
+        let manager = SequenceNumberManager::default();
+        let source = FakeSource::default();
+
+        let _held = manager.lease(&source, AccountAddress::ONE).await.unwrap();
+        let other = tokio::time::timeout(
+            Duration::from_secs(1),
+            manager.lease(&source, AccountAddress::from_hex_literal("0x2").unwrap()),
+        )
+        .await;
+        assert!(other.is_ok());
+
+        let same = tokio::time::timeout(
+            Duration::from_millis(50),
+            manager.lease(&source, AccountAddress::ONE),
+        )
+        .await;
+        assert!(same.is_err());
+    }
+
+    #[test]
+    fn test_is_expired_unused() {
+        // committed
+        assert!(!is_expired_unused(4, 100, 5, 200));
+        // still pending
+        assert!(!is_expired_unused(4, 100, 4, 90));
+        assert!(is_expired_unused(4, 100, 4, 101));
+        assert!(is_expired_unused(6, 100, 4, 101));
+    }
+}
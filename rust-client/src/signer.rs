@@ -0,0 +1,387 @@
+use std::{fmt::Debug, fs, path::Path};
+
+use aptos_sdk::{
+    bcs,
+    crypto::{
+        Signature, SigningKey, ValidCryptoMaterialStringExt,
+        ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+        signing_message,
+    },
+    move_types::account_address::AccountAddress,
+    types::{
+        LocalAccount,
+        transaction::{
+            RawTransaction, RawTransactionWithData, SignedTransaction,
+            authenticator::{AccountAuthenticator, AuthenticationKey},
+        },
+    },
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{Error, Result};
+
+/// Signs transactions on behalf of a single account.
+/// `Client` uses it for every entry function it submits.
+#[async_trait]
+pub trait TransactionSigner: Debug + Send + Sync {
+    fn address(&self) -> AccountAddress;
+
+    /// Used to build unsigned transactions for simulation
+    fn public_key(&self) -> Ed25519PublicKey;
+
+    async fn sign(&self, txn: RawTransaction) -> Result<SignedTransaction>;
+
+    /// Signs as the sender or a secondary signer of a multi-agent transaction
+    async fn sign_multi_agent(
+        &self,
+        _txn: &RawTransactionWithData,
+    ) -> Result<AccountAuthenticator> {
+        Err(Error::Unsupported(
+            "multi-agent signing is not implemented by this signer".into(),
+        ))
+    }
+}
+
+fn sign_multi_agent_with_key(
+    private_key: &Ed25519PrivateKey,
+    public_key: &Ed25519PublicKey,
+    txn: &RawTransactionWithData,
+) -> Result<AccountAuthenticator> {
+    let message = signing_message(txn).map_err(|e| Error::Other(e.into()))?;
+    let signature = private_key.sign_arbitrary_message(&message);
+
+    Ok(AccountAuthenticator::ed25519(public_key.clone(), signature))
+}
+
+#[async_trait]
+impl TransactionSigner for LocalAccount {
+    fn address(&self) -> AccountAddress {
+        LocalAccount::address(self)
+    }
+
+    fn public_key(&self) -> Ed25519PublicKey {
+        LocalAccount::public_key(self).clone()
+    }
+
+    async fn sign(&self, txn: RawTransaction) -> Result<SignedTransaction> {
+        Ok(self.sign_transaction(txn))
+    }
+
+    async fn sign_multi_agent(&self, txn: &RawTransactionWithData) -> Result<AccountAuthenticator> {
+        sign_multi_agent_with_key(self.private_key(), LocalAccount::public_key(self), txn)
+    }
+}
+
+/// Signs with an Ed25519 private key loaded from a file
+#[derive(Debug)]
+pub struct Ed25519KeyFileSigner {
+    address: AccountAddress,
+    private_key: Ed25519PrivateKey,
+    public_key: Ed25519PublicKey,
+}
+
+impl Ed25519KeyFileSigner {
+    /// Reads a hex encoded private key, with or without the `0x` or `ed25519-priv-0x` prefix.
+    /// The account address is derived from the key.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("failed to read {}: {}", path.display(), e)))?;
+        let encoded = content.trim();
+        let encoded = encoded.strip_prefix("ed25519-priv-").unwrap_or(encoded);
+
+        let private_key = Ed25519PrivateKey::from_encoded_string(encoded)
+            .map_err(|e| Error::Config(format!("invalid key in {}: {}", path.display(), e)))?;
+        let public_key = Ed25519PublicKey::from(&private_key);
+        let address = AuthenticationKey::ed25519(&public_key).account_address();
+
+        Ok(Ed25519KeyFileSigner {
+            address,
+            private_key,
+            public_key,
+        })
+    }
+
+    /// Overrides the derived address, for accounts whose key has been rotated
+    pub fn with_address(mut self, address: AccountAddress) -> Self {
+        self.address = address;
+        self
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for Ed25519KeyFileSigner {
+    fn address(&self) -> AccountAddress {
+        self.address
+    }
+
+    fn public_key(&self) -> Ed25519PublicKey {
+        self.public_key.clone()
+    }
+
+    async fn sign(&self, txn: RawTransaction) -> Result<SignedTransaction> {
+        let signed = txn.sign(&self.private_key, self.public_key.clone())?;
+
+        Ok(signed.into_inner())
+    }
+
+    async fn sign_multi_agent(&self, txn: &RawTransactionWithData) -> Result<AccountAuthenticator> {
+        sign_multi_agent_with_key(&self.private_key, &self.public_key, txn)
+    }
+}
+
+#[derive(Serialize)]
+struct RemoteSignRequest {
+    address: AccountAddress,
+    /// Hex encoded BCS bytes of the raw transaction, or of the `RawTransactionWithData`
+    /// for multi-agent transactions
+    raw_transaction: String,
+    /// Hex encoded message to sign
+    signing_message: String,
+}
+
+#[derive(Deserialize)]
+struct RemoteSignResponse {
+    /// Hex encoded Ed25519 signature
+    signature: String,
+}
+
+/// Delegates signing to an HTTP service holding the key, e.g. a KMS/HSM proxy.
+/// `POST {url}` receives the raw transaction and its signing message as hex
+/// and must answer with `{ "signature": "0x..." }`.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    url: Url,
+    address: AccountAddress,
+    public_key: Ed25519PublicKey,
+    http: reqwest::Client,
+}
+
+impl RemoteSigner {
+    pub fn new(url: Url, address: AccountAddress, public_key: Ed25519PublicKey) -> Self {
+        RemoteSigner {
+            url,
+            address,
+            public_key,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn request_signature(
+        &self,
+        raw_transaction: Vec<u8>,
+        message: Vec<u8>,
+    ) -> Result<Ed25519Signature> {
+        let request = RemoteSignRequest {
+            address: self.address,
+            raw_transaction: format!("0x{}", hex::encode(raw_transaction)),
+            signing_message: format!("0x{}", hex::encode(&message)),
+        };
+
+        let response = self
+            .http
+            .post(self.url.clone())
+            .json(&request)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| Error::Other(e.into()))?
+            .json::<RemoteSignResponse>()
+            .await
+            .map_err(|e| Error::Decode(e.to_string()))?;
+
+        let signature = Ed25519Signature::from_encoded_string(&response.signature)
+            .map_err(|e| Error::Decode(format!("invalid signature: {}", e)))?;
+        signature
+            .verify_arbitrary_msg(&message, &self.public_key)
+            .map_err(|_| {
+                Error::Decode(format!(
+                    "signature does not match the key of {}",
+                    self.address
+                ))
+            })?;
+
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn address(&self) -> AccountAddress {
+        self.address
+    }
+
+    fn public_key(&self) -> Ed25519PublicKey {
+        self.public_key.clone()
+    }
+
+    async fn sign(&self, txn: RawTransaction) -> Result<SignedTransaction> {
+        let message = signing_message(&txn).map_err(|e| Error::Other(e.into()))?;
+        let signature = self
+            .request_signature(bcs::to_bytes(&txn)?, message)
+            .await?;
+
+        Ok(SignedTransaction::new(
+            txn,
+            self.public_key.clone(),
+            signature,
+        ))
+    }
+
+    async fn sign_multi_agent(&self, txn: &RawTransactionWithData) -> Result<AccountAuthenticator> {
+        let message = signing_message(txn).map_err(|e| Error::Other(e.into()))?;
+        let signature = self.request_signature(bcs::to_bytes(txn)?, message).await?;
+
+        Ok(AccountAuthenticator::ed25519(
+            self.public_key.clone(),
+            signature,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aptos_sdk::{
+        move_types::{identifier::Identifier, language_storage::ModuleId},
+        types::{
+            chain_id::ChainId,
+            transaction::{EntryFunction, TransactionPayload},
+        },
+    };
+    use serde_json::Value;
+
+    use super::*;
+    use crate::utils::serve_json;
+
+    fn private_key(seed: u8) -> Ed25519PrivateKey {
+        Ed25519PrivateKey::try_from(&[seed; 32][..]).unwrap()
+    }
+
+    fn raw_transaction(sender: AccountAddress) -> RawTransaction {
+        let payload = TransactionPayload::EntryFunction(EntryFunction::new(
+            ModuleId::new(AccountAddress::ONE, Identifier::new("vault").unwrap()),
+            Identifier::new("deposit").unwrap(),
+            vec![],
+            vec![],
+        ));
+
+        RawTransaction::new(sender, 7, payload, 1000, 100, u64::MAX, ChainId::test())
+    }
+
+    fn write_key_file(key: &Ed25519PrivateKey, name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.key", name, std::process::id()));
+        let encoded = format!("ed25519-priv-0x{}\n", hex::encode(key.to_bytes()));
+        fs::write(&path, encoded).unwrap();
+        path
+    }
+
+    /// Answers the first request with `signature`
+    async fn serve_signature(signature: String) -> (Url, tokio::task::JoinHandle<Vec<Value>>) {
+        let (url, requests) = serve_json(vec![serde_json::json!({ "signature": signature })]).await;
+
+        (url.join("sign").unwrap(), requests)
+    }
+
+    #[tokio::test]
+    async fn test_key_file_signer() {
+        let key = private_key(1);
+        let path = write_key_file(&key, "moneyfi-signer");
+        let signer = Ed25519KeyFileSigner::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let public_key = Ed25519PublicKey::from(&key);
+        assert_eq!(signer.public_key(), public_key);
+        assert_eq!(
+            signer.address(),
+            AuthenticationKey::ed25519(&public_key).account_address()
+        );
+
+        let signed = signer
+            .sign(raw_transaction(signer.address()))
+            .await
+            .unwrap();
+        assert!(signed.verify_signature().is_ok());
+
+        let txn = RawTransactionWithData::new_multi_agent(
+            raw_transaction(AccountAddress::ONE),
+            vec![signer.address()],
+        );
+        let AccountAuthenticator::Ed25519 {
+            public_key,
+            signature,
+        } = signer.sign_multi_agent(&txn).await.unwrap()
+        else {
+            panic!("expected an ed25519 authenticator");
+        };
+        assert_eq!(public_key, signer.public_key());
+        let message = signing_message(&txn).unwrap();
+        assert!(
+            signature
+                .verify_arbitrary_msg(&message, &public_key)
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer() {
+        let key = private_key(2);
+        let public_key = Ed25519PublicKey::from(&key);
+        let address = AuthenticationKey::ed25519(&public_key).account_address();
+        let txn = raw_transaction(address);
+        let message = signing_message(&txn).unwrap();
+        let signature = key.sign_arbitrary_message(&message);
+
+        let (url, request) =
+            serve_signature(format!("0x{}", hex::encode(signature.to_bytes()))).await;
+        let signer = RemoteSigner::new(url, address, public_key);
+        let signed = signer.sign(txn.clone()).await.unwrap();
+        assert!(signed.verify_signature().is_ok());
+
+        let request = &request.await.unwrap()[0];
+        assert_eq!(request["address"], serde_json::to_value(address).unwrap());
+        assert_eq!(
+            request["raw_transaction"],
+            format!("0x{}", hex::encode(bcs::to_bytes(&txn).unwrap()))
+        );
+        assert_eq!(
+            request["signing_message"],
+            format!("0x{}", hex::encode(&message))
+        );
+
+        let multi_agent = RawTransactionWithData::new_multi_agent(txn, vec![]);
+        let message = signing_message(&multi_agent).unwrap();
+        let signature = key.sign_arbitrary_message(&message);
+        let (url, request) =
+            serve_signature(format!("0x{}", hex::encode(signature.to_bytes()))).await;
+        let signer = RemoteSigner::new(url, address, Ed25519PublicKey::from(&key));
+        assert!(signer.sign_multi_agent(&multi_agent).await.is_ok());
+        assert_eq!(
+            request.await.unwrap()[0]["raw_transaction"],
+            format!("0x{}", hex::encode(bcs::to_bytes(&multi_agent).unwrap()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_invalid_signature() {
+        let key = private_key(3);
+        let public_key = Ed25519PublicKey::from(&key);
+        let address = AuthenticationKey::ed25519(&public_key).account_address();
+        let txn = raw_transaction(address);
+
+        // signed by another key
+        let message = signing_message(&txn).unwrap();
+        let signature = private_key(4).sign_arbitrary_message(&message);
+        let (url, _) = serve_signature(format!("0x{}", hex::encode(signature.to_bytes()))).await;
+        let signer = RemoteSigner::new(url, address, public_key.clone());
+        assert!(matches!(
+            signer.sign(txn.clone()).await,
+            Err(Error::Decode(_))
+        ));
+
+        let (url, _) = serve_signature("0x1234".to_string()).await;
+        let signer = RemoteSigner::new(url, address, public_key);
+        assert!(matches!(signer.sign(txn).await, Err(Error::Decode(_))));
+    }
+}
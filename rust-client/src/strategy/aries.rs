@@ -1,26 +1,74 @@
-use std::{str::FromStr, sync::Arc};
+use std::sync::Arc;
 
-use anyhow::Result;
 use aptos_sdk::{
     bcs,
-    move_types::{
-        account_address::AccountAddress, identifier::Identifier, language_storage::ModuleId,
-    },
-    rest_client::aptos_api_types::{EntryFunctionId, TransactionInfo, ViewRequest},
+    move_types::account_address::AccountAddress,
+    rest_client::aptos_api_types::U64,
     types::transaction::EntryFunction,
 };
+use ::aries::ReserveDetail;
 use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
 
 use crate::{
-    Client,
-    strategy::{AssetState, LendingStrategy, Strategy},
+    Client, Error, OrderedMap, Receipt, Result, SimulationResult, TxOptions, WalletId,
+    access_control::Role,
+    strategy::{AssetState, BorrowPower, BorrowingState, LendingStrategy, Strategy},
+    utils::{
+        bytes_arg, deserialize_number_from_string, deserialize_object_address, expect_len,
+        parse_number,
+    },
 };
 
 const MODULE_NAME: &str = "strategy_aries";
 
+/// A leveraged vault of `strategy_aries`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AriesVault {
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub borrow_asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub deposit_cap: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub total_deposited_amount: u128,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub total_withdrawn_amount: u128,
+    /// Pending deposits, compounding dust and swapped assets not yet deposited to Aries
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub available_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub available_borrow_amount: u64,
+    pub rewards: OrderedMap<AccountAddress, U64>,
+    /// wallet account address -> amount not yet deposited to Aries
+    pub pending_amount: OrderedMap<AccountAddress, U64>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub total_shares: u128,
+    /// Shares minted by the vault to itself when looping
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub owned_shares: u128,
+    pub paused: bool,
+}
+
+/// Aries reserves of a vault, read by the APR views
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AriesReserves {
+    /// Address of the Aries package
+    pub aries: AccountAddress,
+    /// Coin type of the reserve of the vault asset, e.g. `<aries>::wrapped_coins::WrappedUSDC`
+    pub asset: String,
+    /// Coin type of the reserve of the borrowed asset
+    pub borrow_asset: String,
+}
+
 pub struct Aries {
     client: Arc<Client>,
     vault_name: String,
+    hook_data: Vec<u8>,
+    reserves: Option<AriesReserves>,
 }
 
 impl Aries {
@@ -28,6 +76,8 @@ impl Aries {
         Aries {
             client,
             vault_name: String::new(),
+            hook_data: vec![],
+            reserves: None,
         }
     }
 
@@ -36,6 +86,108 @@ impl Aries {
 
         self
     }
+
+    /// Emitted in a `vault::HookEvent` on withdraw
+    pub fn with_hook_data(&mut self, hook_data: Vec<u8>) -> &mut Self {
+        self.hook_data = hook_data;
+
+        self
+    }
+
+    pub fn with_reserves(&mut self, reserves: AriesReserves) -> &mut Self {
+        self.reserves = Some(reserves);
+
+        self
+    }
+
+    fn reserves(&self) -> Result<&AriesReserves> {
+        self.reserves
+            .as_ref()
+            .ok_or_else(|| Error::Config("aries reserves are not set".into()))
+    }
+
+    /// Reads `reserve::reserve_state` of the Aries package
+    async fn get_reserve_detail(&self, reserve_type: &str) -> Result<ReserveDetail> {
+        let function = "reserve_state";
+        let data = self
+            .client
+            .view_generic_at(
+                self.reserves()?.aries,
+                "reserve",
+                function,
+                &[reserve_type],
+                vec![],
+            )
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        let mut reserve: ReserveDetail = serde_json::from_value(data[0].clone())?;
+        reserve.token_address = reserve_type.to_string();
+
+        Ok(reserve)
+    }
+
+    fn vault_name_arg(&self) -> Value {
+        Value::String(self.vault_name.clone())
+    }
+
+    /// Deposits the vault's available amount to Aries
+    pub fn vault_deposit_payload(&self, amount: u64) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "vault_deposit",
+            vec![bcs::to_bytes(&self.vault_name)?, bcs::to_bytes(&amount)?],
+        )
+    }
+
+    pub async fn vault_deposit(
+        &self,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let efn = self.vault_deposit_payload(amount)?;
+
+        self.client
+            .send_restricted_tx(efn, &[Role::ServiceAccount], opts)
+            .await
+    }
+
+    pub async fn simulate_vault_deposit(
+        &self,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.vault_deposit_payload(amount)?;
+
+        self.client.simulate(efn, opts).await
+    }
+
+    /// Returns the address and name of every vault
+    pub async fn get_vaults(&self) -> Result<Vec<(AccountAddress, String)>> {
+        let function = "get_vaults";
+        let data = self.client.view(MODULE_NAME, function, vec![]).await?;
+        expect_len(function, &data, 2)?;
+
+        let addresses = serde_json::from_value::<Vec<AccountAddress>>(data[0].clone())?;
+        let names = serde_json::from_value::<Vec<String>>(data[1].clone())?;
+
+        Ok(addresses.into_iter().zip(names).collect())
+    }
+
+    /// Returns the address and state of the configured vault
+    pub async fn get_vault(&self) -> Result<(AccountAddress, AriesVault)> {
+        let function = "get_vault";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![self.vault_name_arg()])
+            .await?;
+        expect_len(function, &data, 2)?;
+
+        Ok((
+            serde_json::from_value(data[0].clone())?,
+            serde_json::from_value(data[1].clone())?,
+        ))
+    }
 }
 
 #[async_trait]
@@ -48,77 +200,156 @@ impl Strategy for Aries {
         self.client.clone()
     }
 
-    async fn deposit(
+    /// The vault's asset is fixed at creation, `asset` is not sent
+    fn deposit_payload(
         &self,
-        wallet_id: Vec<u8>,
-        asset: AccountAddress,
+        wallet_id: WalletId,
+        _asset: AccountAddress,
         amount: u64,
-    ) -> Result<TransactionInfo> {
-        let efn = EntryFunction::new(
-            ModuleId {
-                address: self.client.contract_address,
-                name: Identifier::from_str(MODULE_NAME)?,
-            },
-            Identifier::from_str("deposit")?,
-            vec![],
+    ) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "deposit",
             vec![
                 bcs::to_bytes(&self.vault_name)?,
                 bcs::to_bytes(&wallet_id)?,
-                bcs::to_bytes(&asset)?,
                 bcs::to_bytes(&amount)?,
             ],
-        );
-
-        self.client.send_tx(efn).await
+        )
     }
 
-    async fn withdraw(
+    /// The vault's asset is fixed at creation, `asset` is not sent
+    fn withdraw_payload(
         &self,
-        wallet_id: Vec<u8>,
-        asset: AccountAddress,
+        wallet_id: WalletId,
+        _asset: AccountAddress,
         amount: u64,
         gas_fee: u64,
-    ) -> Result<TransactionInfo> {
-        let swap_slippage = 100; // TODO: determine correct value
-
-        let efn = EntryFunction::new(
-            ModuleId {
-                address: self.client.contract_address,
-                name: Identifier::from_str(self.module_name().as_str())?,
-            },
-            Identifier::from_str("withdraw")?,
-            vec![],
-            vec![
-                bcs::to_bytes(&self.vault_name)?,
-                bcs::to_bytes(&wallet_id)?,
-                bcs::to_bytes(&asset)?,
-                bcs::to_bytes(&amount)?,
-                bcs::to_bytes(&gas_fee)?,
-                bcs::to_bytes(&swap_slippage)?,
-            ],
-        );
+    ) -> Result<EntryFunction> {
+        let mut args = vec![
+            bcs::to_bytes(&self.vault_name)?,
+            bcs::to_bytes(&wallet_id)?,
+            bcs::to_bytes(&amount)?,
+            bcs::to_bytes(&gas_fee)?,
+        ];
+        let function = if self.hook_data.is_empty() {
+            "withdraw"
+        } else {
+            args.push(bcs::to_bytes(&self.hook_data)?);
+            "withdraw_with_hook_data"
+        };
 
-        self.client.send_tx(efn).await
+        self.client.entry_function(MODULE_NAME, function, args)
     }
 
     async fn get_account_state(
         &self,
-        wallet_id: Vec<u8>,
-        asset: AccountAddress,
+        wallet_id: WalletId,
+        _asset: AccountAddress,
     ) -> Result<AssetState> {
-        todo!()
+        let function = "get_account_state";
+        let data = self
+            .client
+            .view(
+                MODULE_NAME,
+                function,
+                vec![self.vault_name_arg(), bytes_arg(wallet_id.as_bytes())],
+            )
+            .await?;
+        expect_len(function, &data, 3)?;
+
+        Ok(AssetState {
+            pending_amount: parse_number(&data[0])?,
+            deposited_amount: parse_number(&data[1])?,
+            est_withdrawable_amount: parse_number(&data[2])?,
+        })
     }
 }
 
 #[async_trait]
 impl LendingStrategy for Aries {
-    async fn borrow_and_deposit(
-        &self,
-        asset: aptos_sdk::types::PeerId,
-        amount: u64,
-    ) -> Result<TransactionInfo> {
-        todo!()
+    async fn check_for_repay(&self) -> Result<u64> {
+        self.get_loan_amount().await
+    }
+
+    async fn check_for_borrow(&self) -> Result<u64> {
+        self.get_max_borrow_amount().await
+    }
+
+    async fn get_borrowing_state(&self) -> Result<BorrowingState> {
+        let function = "get_borrowing_state";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![self.vault_name_arg()])
+            .await?;
+        expect_len(function, &data, 3)?;
+
+        Ok(BorrowingState {
+            loan_amount: parse_number(&data[0])?,
+            owned_deposited_amount: parse_number(&data[1])?,
+            est_repay_amount: parse_number(&data[2])?,
+        })
+    }
+
+    async fn get_borrow_power(&self) -> Result<BorrowPower> {
+        let function = "get_borrow_power";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![self.vault_name_arg()])
+            .await?;
+        expect_len(function, &data, 2)?;
+
+        Ok(BorrowPower {
+            available: parse_number(&data[0])?,
+            total: parse_number(&data[1])?,
+        })
+    }
+
+    async fn get_deposit_apr(&self) -> Result<f64> {
+        let reserve = self.get_reserve_detail(&self.reserves()?.asset).await?;
+
+        Ok(reserve.get_deposit_apr())
+    }
+
+    async fn get_borrow_apr(&self) -> Result<f64> {
+        let reserve = self.get_reserve_detail(&self.reserves()?.borrow_asset).await?;
+
+        Ok(reserve.get_borrow_apr())
     }
 
-    // TODO: 
+    async fn get_max_borrow_amount(&self) -> Result<u64> {
+        let function = "get_max_borrow_amount";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![self.vault_name_arg()])
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        parse_number(&data[0])
+    }
+
+    fn borrow_and_deposit_payload(&self, amount: u64) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "borrow_and_deposit",
+            vec![bcs::to_bytes(&self.vault_name)?, bcs::to_bytes(&amount)?],
+        )
+    }
+
+    /// Amounts above the loan are capped on-chain, `u64::MAX` repays everything
+    fn repay_payload(&self, amount: u64) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "repay",
+            vec![bcs::to_bytes(&self.vault_name)?, bcs::to_bytes(&amount)?],
+        )
+    }
+
+    fn compound_payload(&self) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "compound_vault",
+            vec![bcs::to_bytes(&self.vault_name)?],
+        )
+    }
 }
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use aptos_sdk::{
+    bcs,
+    move_types::{account_address::AccountAddress, u256::U256},
+    types::transaction::EntryFunction,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::{
+    Client, Error, OrderedMap, Receipt, Result, SimulationResult, TxOptions, WalletId,
+    access_control::Role,
+    strategy::{
+        AssetState, DexPosition, DexStrategy, Strategy, StrategyID, vault_deposit_payload,
+        vault_withdraw_payload,
+    },
+    utils::{bytes_arg, deserialize_number_from_string, deserialize_object_address, expect_len},
+};
+
+const MODULE_NAME: &str = "strategy_hyperion";
+
+/// Arguments of `strategy_hyperion::pack_extra_data`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperionExtraData {
+    pub fee_tier: u8,
+    pub pool: AccountAddress,
+    pub slippage_numerator: u128,
+    pub slippage_denominator: u128,
+    pub threshold_numerator: u128,
+    pub threshold_denominator: u128,
+    pub withdraw_fee: u64,
+}
+
+impl HyperionExtraData {
+    /// Encodes the fields the same way as `pack_extra_data`
+    pub fn pack(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(vec![
+            bcs::to_bytes(&self.fee_tier)?,
+            bcs::to_bytes(&self.pool)?,
+            bcs::to_bytes(&U256::from(self.slippage_numerator))?,
+            bcs::to_bytes(&U256::from(self.slippage_denominator))?,
+            bcs::to_bytes(&U256::from(self.threshold_numerator))?,
+            bcs::to_bytes(&U256::from(self.threshold_denominator))?,
+            bcs::to_bytes(&self.withdraw_fee)?,
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HyperionStrategyData {
+    pub strategy_id: u8,
+    /// pool address -> position
+    pub pools: OrderedMap<AccountAddress, HyperionPosition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HyperionPosition {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub position: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_amount: u128,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub pair: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    pub fee_tier: u8,
+    pub tick_lower: u32,
+    pub tick_upper: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interest_amount: u64,
+    /// The remaining amount after the last tick update
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub remaining_amount: u64,
+}
+
+impl HyperionPosition {
+    /// Ticks are stored as the two's complement bits of an `i32`
+    pub fn tick_range(&self) -> (i32, i32) {
+        (self.tick_lower as i32, self.tick_upper as i32)
+    }
+}
+
+pub struct Hyperion {
+    client: Arc<Client>,
+    extra_data: Option<HyperionExtraData>,
+    hook_data: Vec<u8>,
+}
+
+impl Hyperion {
+    pub fn new(client: Arc<Client>) -> Self {
+        Hyperion {
+            client,
+            extra_data: None,
+            hook_data: vec![],
+        }
+    }
+
+    pub fn with_extra_data(&mut self, extra_data: HyperionExtraData) -> &mut Self {
+        self.extra_data = Some(extra_data);
+
+        self
+    }
+
+    /// Emitted in a `vault::HookEvent` on withdraw
+    pub fn with_hook_data(&mut self, hook_data: Vec<u8>) -> &mut Self {
+        self.hook_data = hook_data;
+
+        self
+    }
+
+    fn packed_extra_data(&self) -> Result<Vec<Vec<u8>>> {
+        self.extra_data
+            .as_ref()
+            .ok_or_else(|| Error::Config("hyperion extra data is not set".into()))?
+            .pack()
+    }
+
+    pub async fn get_user_strategy_data(
+        &self,
+        wallet_id: WalletId,
+    ) -> Result<HyperionStrategyData> {
+        let function = "get_user_strategy_data";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![bytes_arg(wallet_id.as_bytes())])
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+
+    /// Moves the wallet's positions back into range, the new ticks are computed on-chain
+    pub fn rebalance_payload(&self, wallet_id: WalletId) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "update_tick_entry",
+            vec![
+                bcs::to_bytes(&wallet_id)?,
+                bcs::to_bytes(&self.packed_extra_data()?)?,
+            ],
+        )
+    }
+
+    pub async fn rebalance(&self, wallet_id: WalletId, opts: Option<TxOptions>) -> Result<Receipt> {
+        let efn = self.rebalance_payload(wallet_id)?;
+
+        self.client
+            .send_restricted_tx(efn, &[Role::ServiceAccount], opts)
+            .await
+    }
+
+    pub async fn simulate_rebalance(
+        &self,
+        wallet_id: WalletId,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.rebalance_payload(wallet_id)?;
+
+        self.client.simulate(efn, opts).await
+    }
+}
+
+#[async_trait]
+impl Strategy for Hyperion {
+    fn module_name(&self) -> String {
+        MODULE_NAME.to_string()
+    }
+
+    fn client(&self) -> Arc<Client> {
+        self.client.clone()
+    }
+
+    fn deposit_payload(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+    ) -> Result<EntryFunction> {
+        vault_deposit_payload(
+            &self.client,
+            self.strategy_id(),
+            wallet_id,
+            asset,
+            amount,
+            self.packed_extra_data()?,
+        )
+    }
+
+    fn withdraw_payload(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+        gas_fee: u64,
+    ) -> Result<EntryFunction> {
+        let mut extra_data = self.packed_extra_data()?;
+        extra_data.push(self.hook_data.clone());
+
+        vault_withdraw_payload(
+            &self.client,
+            self.strategy_id(),
+            wallet_id,
+            asset,
+            amount,
+            gas_fee,
+            extra_data,
+        )
+    }
+
+    async fn get_account_state(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+    ) -> Result<AssetState> {
+        self.get_allocation_state(wallet_id, asset).await
+    }
+}
+
+#[async_trait]
+impl DexStrategy for Hyperion {
+    fn strategy_id(&self) -> StrategyID {
+        StrategyID::Hyperion
+    }
+
+    async fn get_positions(&self, wallet_id: WalletId) -> Result<Vec<DexPosition>> {
+        let data = self.get_user_strategy_data(wallet_id).await?;
+
+        Ok(data
+            .pools
+            .iter()
+            .map(|(_, position)| DexPosition {
+                asset: position.asset,
+                amount: position.amount,
+                pending_amount: position.remaining_amount,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_extra_data() {
+        let extra_data = HyperionExtraData {
+            fee_tier: 1,
+            pool: AccountAddress::from_hex_literal("0xa").unwrap(),
+            slippage_numerator: 1,
+            slippage_denominator: 100,
+            threshold_numerator: 5,
+            threshold_denominator: 1000,
+            withdraw_fee: 250,
+        };
+        let packed = extra_data.pack().unwrap();
+
+        assert_eq!(packed.len(), 7);
+        assert_eq!(packed[0], vec![1]);
+        assert_eq!(packed[1].len(), 32);
+        assert_eq!(packed[1][31], 0xa);
+        assert_eq!(packed[2].len(), 32);
+        assert_eq!(packed[3][0], 100);
+        assert_eq!(packed[5][..2], [0xe8, 0x03]);
+        assert_eq!(packed[6], 250u64.to_le_bytes().to_vec());
+    }
+}
@@ -1,5 +1,13 @@
 mod aries;
+mod hyperion;
+mod registry;
 mod strategy;
+mod tapp;
+mod thala;
 
-pub use aries::*;
+pub use self::aries::*;
+pub use hyperion::*;
+pub use registry::*;
 pub use strategy::*;
+pub use tapp::*;
+pub use thala::*;
@@ -0,0 +1,209 @@
+use std::{str::FromStr, sync::Arc};
+
+use aptos_sdk::move_types::account_address::AccountAddress;
+
+use crate::{
+    Client, Error, Result, TypeInfo,
+    events::MoneyFiEvent,
+    strategy::{DexStrategy, Strategy, StrategyID, StrategyStats},
+    utils::{expect_len, parse_number},
+};
+
+/// A strategy registered in `vault::StrategyRegistry`, supported by the client or not
+#[derive(Debug, Clone)]
+pub struct StrategyHandle {
+    client: Arc<Client>,
+    pub type_info: TypeInfo,
+    /// Deposit address, the only sender allowed to report deposits and withdrawals
+    pub address: AccountAddress,
+}
+
+impl StrategyHandle {
+    pub fn new(client: Arc<Client>, type_info: TypeInfo, address: AccountAddress) -> Self {
+        StrategyHandle {
+            client,
+            type_info,
+            address,
+        }
+    }
+
+    pub fn strategy_id(&self) -> Option<StrategyID> {
+        StrategyID::from_type_info(&self.type_info, self.client.contract_address)
+    }
+
+    /// Whether the event was emitted for this strategy
+    pub fn matches(&self, event: &MoneyFiEvent) -> bool {
+        event.strategy() == Some(&self.type_info)
+    }
+
+    /// Calls the `get_strategy_stats` view of the strategy module, or of the
+    /// legacy `strategy` module for DEX strategies. Best effort: no strategy interface
+    /// requires the view, a module without it fails with `Error::Unsupported`.
+    pub async fn get_strategy_stats(&self, asset: AccountAddress) -> Result<StrategyStats> {
+        if let Some(strategy) = self.dex_strategy() {
+            return strategy.get_strategy_stats(asset).await;
+        }
+
+        let function = "get_strategy_stats";
+        let address = AccountAddress::from_str(&self.type_info.account_address)
+            .map_err(|e| Error::Decode(format!("{}: {}", self.type_info, e)))?;
+        let data = match self
+            .client
+            .view_at(
+                address,
+                &self.type_info.module_name,
+                function,
+                vec![serde_json::to_value(asset)?],
+            )
+            .await
+        {
+            Err(Error::Transport(e)) if is_missing_function(&e.to_string()) => {
+                return Err(Error::Unsupported(format!(
+                    "{} has no {} view",
+                    self.type_info, function
+                )));
+            }
+            res => res?,
+        };
+        expect_len(function, &data, 3)?;
+
+        Ok(StrategyStats {
+            total_value_locked: parse_number(&data[0])?,
+            total_deposited: parse_number(&data[1])?,
+            total_withdrawn: parse_number(&data[2])?,
+        })
+    }
+
+    fn dex_strategy(&self) -> Option<Box<dyn DexStrategy>> {
+        let strategy: Box<dyn DexStrategy> = match self.strategy_id()? {
+            StrategyID::Hyperion => Box::new(self.client.strategy_hyperion()),
+            StrategyID::ThalaSwap => Box::new(self.client.strategy_thala()),
+            StrategyID::TappExchange => Box::new(self.client.strategy_tapp()),
+            StrategyID::AriesMarket => return None,
+        };
+
+        Some(strategy)
+    }
+}
+
+/// Entry of `Client::strategies`
+pub enum RegisteredStrategy {
+    /// A strategy implemented by the client, one per vault for Aries
+    Known {
+        id: StrategyID,
+        strategy: Box<dyn Strategy>,
+        handle: StrategyHandle,
+        /// Aries vault the strategy is bound to
+        vault_name: Option<String>,
+    },
+    /// A strategy registered after this client was released, read-only
+    Unknown(StrategyHandle),
+}
+
+impl RegisteredStrategy {
+    pub fn handle(&self) -> &StrategyHandle {
+        match self {
+            RegisteredStrategy::Known { handle, .. } => handle,
+            RegisteredStrategy::Unknown(handle) => handle,
+        }
+    }
+
+    pub fn strategy(&self) -> Option<&dyn Strategy> {
+        match self {
+            RegisteredStrategy::Known { strategy, .. } => Some(strategy.as_ref()),
+            RegisteredStrategy::Unknown(_) => None,
+        }
+    }
+
+    pub fn strategy_id(&self) -> Option<StrategyID> {
+        match self {
+            RegisteredStrategy::Known { id, .. } => Some(*id),
+            RegisteredStrategy::Unknown(_) => None,
+        }
+    }
+
+    pub fn type_info(&self) -> &TypeInfo {
+        &self.handle().type_info
+    }
+
+    pub fn vault_name(&self) -> Option<&str> {
+        match self {
+            RegisteredStrategy::Known { vault_name, .. } => vault_name.as_deref(),
+            RegisteredStrategy::Unknown(_) => None,
+        }
+    }
+}
+
+impl Client {
+    /// Returns the client implementation of a strategy, Aries without a vault name
+    pub fn strategy(&self, id: StrategyID) -> Box<dyn Strategy> {
+        match id {
+            StrategyID::Hyperion => Box::new(self.strategy_hyperion()),
+            StrategyID::AriesMarket => Box::new(self.strategy_aries()),
+            StrategyID::ThalaSwap => Box::new(self.strategy_thala()),
+            StrategyID::TappExchange => Box::new(self.strategy_tapp()),
+        }
+    }
+
+    /// Discovers the strategies registered in the vault, Aries once per vault
+    pub async fn strategies(&self) -> Result<Vec<RegisteredStrategy>> {
+        let client = Arc::new(self.clone());
+        let registry = self.get_strategy_registry().await?;
+
+        let mut strategies = vec![];
+        for entry in registry.entries {
+            let handle = StrategyHandle::new(client.clone(), entry.key, entry.value);
+            match handle.strategy_id() {
+                Some(StrategyID::AriesMarket) => {
+                    for (_, vault_name) in self.strategy_aries().get_vaults().await? {
+                        let mut aries = self.strategy_aries();
+                        aries.with_vault_name(vault_name.clone());
+                        strategies.push(RegisteredStrategy::Known {
+                            id: StrategyID::AriesMarket,
+                            strategy: Box::new(aries),
+                            handle: handle.clone(),
+                            vault_name: Some(vault_name),
+                        });
+                    }
+                }
+                Some(id) => strategies.push(RegisteredStrategy::Known {
+                    id,
+                    strategy: self.strategy(id),
+                    handle,
+                    vault_name: None,
+                }),
+                None => strategies.push(RegisteredStrategy::Unknown(handle)),
+            }
+        }
+
+        Ok(strategies)
+    }
+}
+
+/// Whether a view failed because the module or the function does not exist
+fn is_missing_function(message: &str) -> bool {
+    [
+        "FUNCTION_RESOLUTION_FAILURE",
+        "LINKER_ERROR",
+        "is not marked as a view function",
+        "could not find",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_missing_function() {
+        assert!(is_missing_function(
+            "API error Error(InvalidInput): FUNCTION_RESOLUTION_FAILURE"
+        ));
+        assert!(is_missing_function(
+            "Function 0x1::strategy_x::get_strategy_stats is not marked as a view function"
+        ));
+        assert!(!is_missing_function("Move abort in 0xabc::strategy_x: 0x1"));
+    }
+}
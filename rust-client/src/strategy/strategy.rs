@@ -1,20 +1,21 @@
 use std::{str::FromStr, sync::Arc};
 
-use anyhow::{Result, anyhow};
 use aptos_sdk::{
-    move_types::{
-        account_address::AccountAddress, 
-    },
-    rest_client::aptos_api_types::{
-        Address, EntryFunctionId, IdentifierWrapper, MoveModuleId, TransactionInfo, ViewRequest,
-    },
+    bcs,
+    move_types::account_address::AccountAddress,
+    types::transaction::EntryFunction,
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::Client;
+use crate::{
+    Client, Error, Receipt, Result, SimulationResult, TxOptions, TypeInfo, WalletId,
+    access_control::Role,
+    utils::{bytes_arg, expect_len, parse_number},
+};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 pub enum StrategyID {
     Hyperion = 1,
     AriesMarket,
@@ -22,25 +23,149 @@ pub enum StrategyID {
     TappExchange,
 }
 
+impl StrategyID {
+    /// Decodes the legacy `u8` strategy of deprecated events and views
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(StrategyID::Hyperion),
+            2 => Some(StrategyID::AriesMarket),
+            3 => Some(StrategyID::ThalaSwap),
+            4 => Some(StrategyID::TappExchange),
+            _ => None,
+        }
+    }
+
+    /// Returns the client-supported strategy of a registered type, `None` for other strategies
+    pub fn from_type_info(type_info: &TypeInfo, contract: AccountAddress) -> Option<Self> {
+        let address = AccountAddress::from_str(&type_info.account_address).ok()?;
+        if address != contract {
+            return None;
+        }
+
+        StrategyID::from_str(&type_info.module_name).ok()
+    }
+}
+
 impl FromStr for StrategyID {
-    type Err = anyhow::Error;
+    type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "strategy_hyperion" => Ok(StrategyID::Hyperion),
             "strategy_aries" => Ok(StrategyID::AriesMarket),
             "strategy_thala" => Ok(StrategyID::ThalaSwap),
             "strategy_tapp" => Ok(StrategyID::TappExchange),
-            _ => Err(anyhow!("unknown strategy: {}", s)),
+            _ => Err(Error::Decode(format!("unknown strategy: {}", s))),
         }
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct AssetState {
     pub pending_amount: u64,
     pub deposited_amount: u64,
     pub est_withdrawable_amount: u64,
 }
 
+/// Amounts of one position of a DEX strategy
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DexPosition {
+    pub asset: AccountAddress,
+    pub amount: u64,
+    /// Held by the strategy outside of the pool
+    pub pending_amount: u64,
+}
+
+/// Totals returned by `strategy::get_strategy_stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StrategyStats {
+    pub total_value_locked: u128,
+    pub total_deposited: u128,
+    pub total_withdrawn: u128,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BorrowingState {
+    pub loan_amount: u64,
+    /// Amount deposited with borrowed fund
+    pub owned_deposited_amount: u64,
+    /// Estimated amount to swap to cover the loan
+    pub est_repay_amount: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BorrowPower {
+    pub available: u64,
+    pub total: u64,
+}
+
+/// Leverage target of a lending loop, measured on the borrow power of the strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopTarget {
+    /// Used borrow power over total borrow power, in bps
+    LoanToValueBps(u64),
+    /// Total borrow power over used borrow power, in bps (e.g. 15_000 for 1.5)
+    HealthFactorBps(u64),
+}
+
+impl LoopTarget {
+    pub fn loan_to_value_bps(&self) -> u64 {
+        match *self {
+            LoopTarget::LoanToValueBps(ltv) => ltv.min(BPS),
+            LoopTarget::HealthFactorBps(0) => BPS,
+            LoopTarget::HealthFactorBps(hf) => (BPS * BPS / hf).min(BPS),
+        }
+    }
+}
+
+/// Bounds of `LendingStrategy::loop_borrow` and `loop_repay`
+#[derive(Debug, Clone)]
+pub struct LoopOptions {
+    pub target: LoopTarget,
+    pub max_iterations: u8,
+    /// Amounts below this are not worth a transaction
+    pub dust_threshold: u64,
+    pub tx_options: Option<TxOptions>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoopSummary {
+    pub iterations: u8,
+    pub total_amount: u64,
+}
+
+const BPS: u64 = 10_000;
+
+/// Scales `max_borrow_amount` down to the part of the available power below the target
+fn borrow_amount_for_target(
+    power: &BorrowPower,
+    max_borrow_amount: u64,
+    target_ltv_bps: u64,
+) -> u64 {
+    if power.total == 0 || power.available == 0 {
+        return 0;
+    }
+
+    let used = power.total.saturating_sub(power.available) as u128;
+    let target_used = power.total as u128 * target_ltv_bps as u128 / BPS as u128;
+    if used >= target_used {
+        return 0;
+    }
+
+    let room = (target_used - used).min(power.available as u128);
+    (max_borrow_amount as u128 * room / power.available as u128) as u64
+}
+
+/// Share of the loan to repay for the used power to fall back to the target
+fn repay_amount_for_target(power: &BorrowPower, loan_amount: u64, target_ltv_bps: u64) -> u64 {
+    let used = power.total.saturating_sub(power.available) as u128;
+    let target_used = power.total as u128 * target_ltv_bps as u128 / BPS as u128;
+    if used == 0 || used <= target_used {
+        return 0;
+    }
+
+    (loan_amount as u128 * (used - target_used)).div_ceil(used) as u64
+}
+
 pub struct StrategyOptions {
     swap_slippage: Option<u64>,
     // TODO: add more opt here
@@ -51,26 +176,80 @@ pub trait Strategy: Send + Sync {
     fn module_name(&self) -> String;
     fn client(&self) -> Arc<Client>;
 
+    fn deposit_payload(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+    ) -> Result<EntryFunction>;
+
+    fn withdraw_payload(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+        gas_fee: u64,
+    ) -> Result<EntryFunction>;
+
     /// Deposit fund from wallet account to strategy vault
     async fn deposit(
         &self,
-        wallet_id: Vec<u8>,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let efn = self.deposit_payload(wallet_id, asset, amount)?;
+
+        self.client()
+            .send_restricted_tx(efn, &[Role::ServiceAccount], opts)
+            .await
+    }
+
+    async fn simulate_deposit(
+        &self,
+        wallet_id: WalletId,
         asset: AccountAddress,
         amount: u64,
-    ) -> Result<TransactionInfo>;
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.deposit_payload(wallet_id, asset, amount)?;
+
+        self.client().simulate(efn, opts).await
+    }
 
     /// Withdraw fund from strategy vault to wallet account
     async fn withdraw(
         &self,
-        wallet_id: Vec<u8>,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+        gas_fee: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let efn = self.withdraw_payload(wallet_id, asset, amount, gas_fee)?;
+
+        self.client()
+            .send_restricted_tx(efn, &[Role::ServiceAccount], opts)
+            .await
+    }
+
+    async fn simulate_withdraw(
+        &self,
+        wallet_id: WalletId,
         asset: AccountAddress,
         amount: u64,
         gas_fee: u64,
-    ) -> Result<TransactionInfo>;
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.withdraw_payload(wallet_id, asset, amount, gas_fee)?;
+
+        self.client().simulate(efn, opts).await
+    }
 
     async fn get_account_state(
         &self,
-        wallet_id: Vec<u8>,
+        wallet_id: WalletId,
         asset: AccountAddress,
     ) -> Result<AssetState>;
 }
@@ -80,69 +259,363 @@ pub trait LendingStrategy: Strategy {
     /// Return amount should be repaid
     async fn check_for_repay(&self) -> Result<u64>;
 
+    /// Return amount can be borrowed, capped by `get_max_borrow_amount`
     async fn check_for_borrow(&self) -> Result<u64>;
 
-    async fn loop_borrow(&self, max: u8) -> Result<()> {
-        let mut i = 0;
-        while i < max {
-            let amount = self.check_for_borrow().await?;
-            if amount == 0 {
+    /// Borrows and deposits until the target leverage is reached.
+    /// Stops early when the borrowable amount drops below the dust threshold.
+    async fn loop_borrow(&self, opts: &LoopOptions) -> Result<LoopSummary> {
+        let mut summary = LoopSummary::default();
+        while summary.iterations < opts.max_iterations {
+            let borrowable_amount = self.check_for_borrow().await?;
+            if borrowable_amount < opts.dust_threshold {
+                break;
+            }
+
+            let power = self.get_borrow_power().await?;
+            let amount = borrow_amount_for_target(
+                &power,
+                borrowable_amount,
+                opts.target.loan_to_value_bps(),
+            );
+            if amount == 0 || amount < opts.dust_threshold {
                 break;
             }
 
-            self.borrow_and_deposit(amount);
+            self.borrow_and_deposit(amount, opts.tx_options.clone()).await?;
 
-            i+= 1;
+            summary.iterations += 1;
+            summary.total_amount += amount;
         }
 
-        Ok(())
+        Ok(summary)
     }
 
-    async fn loop_repay(&self) -> Result<()>;
+    /// Repays until the leverage is back under the target
+    async fn loop_repay(&self, opts: &LoopOptions) -> Result<LoopSummary> {
+        let mut summary = LoopSummary::default();
+        while summary.iterations < opts.max_iterations {
+            let power = self.get_borrow_power().await?;
+            let amount = repay_amount_for_target(
+                &power,
+                self.check_for_repay().await?,
+                opts.target.loan_to_value_bps(),
+            );
+            if amount == 0 || amount < opts.dust_threshold {
+                break;
+            }
 
-    async fn get_deposited_amount(&self) -> Result<u64>; 
-    async fn get_loan_amount(&self) -> Result<u64>; 
+            self.repay(amount, opts.tx_options.clone()).await?;
 
-    /// Return deposit APR and reward APR
-    async fn get_deposit_apr(&self) -> Result<u64, u64>;
+            summary.iterations += 1;
+            summary.total_amount += amount;
+        }
 
-    /// Return borrow APR and reward APR
-    async fn get_borrow_apr(&self) -> Result<u64, u64>;
+        Ok(summary)
+    }
 
-    async fn borrow_and_deposit(&self, amount: u64) -> Result<TransactionInfo>;
+    async fn get_borrowing_state(&self) -> Result<BorrowingState>;
 
-    async fn repay(&self, asset: AccountAddress, amount: u64) -> Result<TransactionInfo>;
+    async fn get_borrow_power(&self) -> Result<BorrowPower>;
 
-    async fn get_max_borrow_amount(&self,) -> Result<u64> {
-        let client = self.client();
+    /// Return deposit APR of the supplied asset, without farming rewards
+    async fn get_deposit_apr(&self) -> Result<f64>;
 
-        let data = client
-            .aptos_client
-            .view(
-                &ViewRequest {
-                    function: EntryFunctionId {
-                        module: MoveModuleId {
-                            address: Address::from(client.contract_address),
-                            name: IdentifierWrapper::from_str(self.module_name().as_str())?,
-                        },
-                        name: IdentifierWrapper::from_str("get_max_borrow_amount")?,
-                    },
-                    type_arguments: vec![],
-                    arguments: vec![],
-                },
-                None,
-            )
-            .await?
-            .into_inner();
-        assert!(data.len() == 1);
+    /// Return borrow APR of the borrowed asset, without farming rewards
+    async fn get_borrow_apr(&self) -> Result<f64>;
+
+    async fn get_max_borrow_amount(&self) -> Result<u64>;
+
+    /// Return amount deposited with borrowed fund
+    async fn get_deposited_amount(&self) -> Result<u64> {
+        Ok(self.get_borrowing_state().await?.owned_deposited_amount)
+    }
+
+    async fn get_loan_amount(&self) -> Result<u64> {
+        Ok(self.get_borrowing_state().await?.loan_amount)
+    }
+
+    fn borrow_and_deposit_payload(&self, amount: u64) -> Result<EntryFunction>;
+
+    fn repay_payload(&self, amount: u64) -> Result<EntryFunction>;
+
+    /// Claims rewards and deposits them back into the strategy
+    fn compound_payload(&self) -> Result<EntryFunction>;
+
+    async fn borrow_and_deposit(
+        &self,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let efn = self.borrow_and_deposit_payload(amount)?;
+
+        self.client()
+            .send_restricted_tx(efn, &[Role::ServiceAccount], opts)
+            .await
+    }
+
+    async fn simulate_borrow_and_deposit(
+        &self,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.borrow_and_deposit_payload(amount)?;
 
-        let amount = serde_json::from_value::<String>(data[0].clone())?.parse::<u64>()?;
+        self.client().simulate(efn, opts).await
+    }
+
+    async fn repay(&self, amount: u64, opts: Option<TxOptions>) -> Result<Receipt> {
+        let efn = self.repay_payload(amount)?;
+
+        self.client()
+            .send_restricted_tx(efn, &[Role::ServiceAccount], opts)
+            .await
+    }
+
+    async fn simulate_repay(
+        &self,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.repay_payload(amount)?;
+
+        self.client().simulate(efn, opts).await
+    }
 
-        Ok(amount)
+    async fn compound(&self, opts: Option<TxOptions>) -> Result<Receipt> {
+        let efn = self.compound_payload()?;
+
+        self.client()
+            .send_restricted_tx(efn, &[Role::ServiceAccount], opts)
+            .await
+    }
+
+    async fn simulate_compound(&self, opts: Option<TxOptions>) -> Result<SimulationResult> {
+        let efn = self.compound_payload()?;
+
+        self.client().simulate(efn, opts).await
     }
 }
 
+/// Strategies routed through `vault::deposit_to_strategy`/`withdraw_from_strategy`
 #[async_trait]
 pub trait DexStrategy: Strategy {
-    async fn rebalance(&self, lower_tick: i64, upper_tick: i64) -> Result<TransactionInfo>;
+    fn strategy_id(&self) -> StrategyID;
+
+    /// Returns the positions of the wallet from `get_user_strategy_data`
+    async fn get_positions(&self, wallet_id: WalletId) -> Result<Vec<DexPosition>>;
+
+    /// `AssetState` of the wallet from its positions and asset allocation.
+    /// `est_withdrawable_amount` only counts the `asset` side of the positions.
+    async fn get_allocation_state(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+    ) -> Result<AssetState> {
+        let allocation = self.get_user_asset_allocation(wallet_id).await?;
+        if allocation.is_empty() {
+            return Ok(AssetState::default());
+        }
+
+        let mut state = AssetState::default();
+        for position in self.get_positions(wallet_id).await? {
+            if position.asset == asset {
+                state.pending_amount += position.pending_amount;
+                state.deposited_amount += position.amount;
+            }
+        }
+        state.est_withdrawable_amount = allocation
+            .iter()
+            .filter(|(address, _)| *address == asset)
+            .map(|(_, amount)| amount)
+            .sum::<u64>()
+            + state.pending_amount;
+
+        Ok(state)
+    }
+
+    /// Returns the assets held by the wallet's positions and their amounts
+    async fn get_user_asset_allocation(
+        &self,
+        wallet_id: WalletId,
+    ) -> Result<Vec<(AccountAddress, u64)>> {
+        let function = "get_user_asset_allocation";
+        let data = self
+            .client()
+            .view(&self.module_name(), function, vec![bytes_arg(wallet_id.as_bytes())])
+            .await?;
+        expect_len(function, &data, 2)?;
+
+        let assets = serde_json::from_value::<Vec<AccountAddress>>(data[0].clone())?;
+        let amounts = serde_json::from_value::<Vec<Value>>(data[1].clone())?
+            .iter()
+            .map(parse_number::<u64>)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(assets.into_iter().zip(amounts).collect())
+    }
+
+    /// Returns the interest earned by the wallet, in USDC
+    async fn get_profit(&self, wallet_id: WalletId) -> Result<u64> {
+        let function = "get_profit";
+        let data = self
+            .client()
+            .view(&self.module_name(), function, vec![bytes_arg(wallet_id.as_bytes())])
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        parse_number(&data[0])
+    }
+
+    async fn get_strategy_stats(&self, asset: AccountAddress) -> Result<StrategyStats> {
+        let function = "get_strategy_stats";
+        let data = self
+            .client()
+            .view(
+                "strategy",
+                function,
+                vec![
+                    serde_json::to_value(self.strategy_id() as u8)?,
+                    serde_json::to_value(asset)?,
+                ],
+            )
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        // the legacy module packs the three values in one vector, empty for other strategies
+        let stats = serde_json::from_value::<Vec<Value>>(data[0].clone())?;
+        expect_len(function, &stats, 3)?;
+
+        Ok(StrategyStats {
+            total_value_locked: parse_number(&stats[0])?,
+            total_deposited: parse_number(&stats[1])?,
+            total_withdrawn: parse_number(&stats[2])?,
+        })
+    }
+}
+
+/// Builds the `vault::deposit_to_strategy` call of a DEX strategy
+pub(crate) fn vault_deposit_payload(
+    client: &Client,
+    strategy_id: StrategyID,
+    wallet_id: WalletId,
+    asset: AccountAddress,
+    amount: u64,
+    extra_data: Vec<Vec<u8>>,
+) -> Result<EntryFunction> {
+    client.entry_function(
+        "vault",
+        "deposit_to_strategy",
+        vec![
+            bcs::to_bytes(&wallet_id)?,
+            bcs::to_bytes(&(strategy_id as u8))?,
+            bcs::to_bytes(&asset)?,
+            bcs::to_bytes(&amount)?,
+            bcs::to_bytes(&extra_data)?,
+        ],
+    )
+}
+
+/// Builds the `vault::withdraw_from_strategy` call of a DEX strategy.
+/// The strategy reads the last element of `extra_data` as hook data.
+pub(crate) fn vault_withdraw_payload(
+    client: &Client,
+    strategy_id: StrategyID,
+    wallet_id: WalletId,
+    asset: AccountAddress,
+    amount: u64,
+    gas_fee: u64,
+    extra_data: Vec<Vec<u8>>,
+) -> Result<EntryFunction> {
+    client.entry_function(
+        "vault",
+        "withdraw_from_strategy",
+        vec![
+            bcs::to_bytes(&wallet_id)?,
+            bcs::to_bytes(&(strategy_id as u8))?,
+            bcs::to_bytes(&asset)?,
+            bcs::to_bytes(&amount)?,
+            bcs::to_bytes(&gas_fee)?,
+            bcs::to_bytes(&extra_data)?,
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loop_amounts() {
+        let power = BorrowPower {
+            available: 600,
+            total: 1000,
+        };
+
+        // 40% used, target 70%: 300 of the 600 available
+        assert_eq!(borrow_amount_for_target(&power, 1200, 7000), 600);
+        assert_eq!(borrow_amount_for_target(&power, 1200, 4000), 0);
+        // 40% used, target 20%: repay half of the loan
+        assert_eq!(repay_amount_for_target(&power, 500, 2000), 250);
+        assert_eq!(repay_amount_for_target(&power, 500, 5000), 0);
+
+        assert_eq!(
+            LoopTarget::HealthFactorBps(20_000).loan_to_value_bps(),
+            5000
+        );
+        assert_eq!(LoopTarget::LoanToValueBps(12_000).loan_to_value_bps(), BPS);
+    }
+
+    #[test]
+    fn test_strategy_id_from_type_info() {
+        let contract = AccountAddress::from_str("0xabc").unwrap();
+        let type_info = |account_address: &str, module_name: &str| TypeInfo {
+            account_address: account_address.to_string(),
+            module_name: module_name.to_string(),
+            struct_name: "Strategy".to_string(),
+        };
+
+        assert_eq!(
+            StrategyID::from_type_info(&type_info("0xabc", "strategy_aries"), contract),
+            Some(StrategyID::AriesMarket)
+        );
+        assert_eq!(
+            StrategyID::from_type_info(&type_info("0xdef", "strategy_aries"), contract),
+            None
+        );
+        assert_eq!(
+            StrategyID::from_type_info(&type_info("0xabc", "strategy_echelon"), contract),
+            None
+        );
+    }
+    #[tokio::test]
+    async fn test_write_requires_service_account() {
+        let registry = serde_json::json!({
+            "type": "0xabc::access_control::Registry",
+            "data": { "accounts": { "entries": [] }, "locked_at": "0" }
+        });
+        let (url, requests) = crate::utils::serve_json(vec![registry]).await;
+
+        let mut client = Client::builder()
+            .rest_url(url.to_string())
+            .chain_id(4)
+            .contract_address("0xabc".into())
+            .build()
+            .unwrap();
+        client.connect(format!("0x{}", "11".repeat(32))).unwrap();
+        let sender = client.signer_address().unwrap();
+        let mut aries = crate::strategy::Aries::new(Arc::new(client));
+        aries.with_vault_name("vault_1".into());
+
+        let err = aries
+            .deposit(WalletId([1; 32]), AccountAddress::ONE, 100, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::MissingRole { account, role: Role::ServiceAccount } if account == sender
+        ));
+        // only the registry was read, nothing was submitted
+        assert_eq!(requests.await.unwrap().len(), 1);
+    }
 }
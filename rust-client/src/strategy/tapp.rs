@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use aptos_sdk::{
+    bcs, move_types::account_address::AccountAddress, types::transaction::EntryFunction,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::{
+    Client, Error, OrderedMap, Result, WalletId,
+    strategy::{
+        AssetState, DexPosition, DexStrategy, Strategy, StrategyID, vault_deposit_payload,
+        vault_withdraw_payload,
+    },
+    utils::{bytes_arg, deserialize_number_from_string, deserialize_object_address, expect_len},
+};
+
+const MODULE_NAME: &str = "strategy_tapp";
+
+/// Arguments of `strategy_tapp::pack_extra_data`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TappExtraData {
+    pub pool: AccountAddress,
+    pub withdraw_fee: u64,
+}
+
+impl TappExtraData {
+    /// Encodes the fields the same way as `pack_extra_data`
+    pub fn pack(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(vec![
+            bcs::to_bytes(&self.pool)?,
+            bcs::to_bytes(&self.withdraw_fee)?,
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TappStrategyData {
+    pub strategy_id: u8,
+    /// pool address -> position
+    pub pools: OrderedMap<AccountAddress, TappPosition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TappPosition {
+    pub position: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_amount: u128,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub pair: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+}
+
+pub struct Tapp {
+    client: Arc<Client>,
+    extra_data: Option<TappExtraData>,
+    hook_data: Vec<u8>,
+}
+
+impl Tapp {
+    pub fn new(client: Arc<Client>) -> Self {
+        Tapp {
+            client,
+            extra_data: None,
+            hook_data: vec![],
+        }
+    }
+
+    pub fn with_extra_data(&mut self, extra_data: TappExtraData) -> &mut Self {
+        self.extra_data = Some(extra_data);
+
+        self
+    }
+
+    /// Emitted in a `vault::HookEvent` on withdraw
+    pub fn with_hook_data(&mut self, hook_data: Vec<u8>) -> &mut Self {
+        self.hook_data = hook_data;
+
+        self
+    }
+
+    fn packed_extra_data(&self) -> Result<Vec<Vec<u8>>> {
+        self.extra_data
+            .as_ref()
+            .ok_or_else(|| Error::Config("tapp extra data is not set".into()))?
+            .pack()
+    }
+
+    pub async fn get_user_strategy_data(&self, wallet_id: WalletId) -> Result<TappStrategyData> {
+        let function = "get_user_strategy_data";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![bytes_arg(wallet_id.as_bytes())])
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+}
+
+#[async_trait]
+impl Strategy for Tapp {
+    fn module_name(&self) -> String {
+        MODULE_NAME.to_string()
+    }
+
+    fn client(&self) -> Arc<Client> {
+        self.client.clone()
+    }
+
+    fn deposit_payload(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+    ) -> Result<EntryFunction> {
+        vault_deposit_payload(
+            &self.client,
+            self.strategy_id(),
+            wallet_id,
+            asset,
+            amount,
+            self.packed_extra_data()?,
+        )
+    }
+
+    fn withdraw_payload(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+        gas_fee: u64,
+    ) -> Result<EntryFunction> {
+        let mut extra_data = self.packed_extra_data()?;
+        extra_data.push(self.hook_data.clone());
+
+        vault_withdraw_payload(
+            &self.client,
+            self.strategy_id(),
+            wallet_id,
+            asset,
+            amount,
+            gas_fee,
+            extra_data,
+        )
+    }
+
+    async fn get_account_state(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+    ) -> Result<AssetState> {
+        self.get_allocation_state(wallet_id, asset).await
+    }
+}
+
+#[async_trait]
+impl DexStrategy for Tapp {
+    fn strategy_id(&self) -> StrategyID {
+        StrategyID::TappExchange
+    }
+
+    async fn get_positions(&self, wallet_id: WalletId) -> Result<Vec<DexPosition>> {
+        let data = self.get_user_strategy_data(wallet_id).await?;
+
+        Ok(data
+            .pools
+            .iter()
+            .map(|(_, position)| DexPosition {
+                asset: position.asset,
+                amount: position.amount,
+                pending_amount: 0,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_extra_data() {
+        let extra_data = TappExtraData {
+            pool: AccountAddress::from_hex_literal("0xc").unwrap(),
+            withdraw_fee: 42,
+        };
+        let packed = extra_data.pack().unwrap();
+
+        assert_eq!(packed.len(), 2);
+        assert_eq!(
+            packed[0],
+            AccountAddress::from_hex_literal("0xc").unwrap().to_vec()
+        );
+        assert_eq!(packed[1], 42u64.to_le_bytes().to_vec());
+    }
+}
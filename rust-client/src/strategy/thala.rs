@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use aptos_sdk::{
+    bcs, move_types::account_address::AccountAddress, types::transaction::EntryFunction,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::{
+    Client, Error, OrderedMap, Result, WalletId,
+    strategy::{
+        AssetState, DexPosition, DexStrategy, Strategy, StrategyID, vault_deposit_payload,
+        vault_withdraw_payload,
+    },
+    utils::{bytes_arg, deserialize_number_from_string, deserialize_object_address, expect_len},
+};
+
+const MODULE_NAME: &str = "strategy_thala";
+
+/// Arguments of `strategy_thala::pack_extra_data`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThalaExtraData {
+    pub pool: AccountAddress,
+    /// Reward id of the first farming reward
+    pub reward_a: String,
+    /// Reward id of the second farming reward
+    pub reward_b: String,
+    pub withdraw_fee: u64,
+}
+
+impl ThalaExtraData {
+    /// Encodes the fields the same way as `pack_extra_data`
+    pub fn pack(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(vec![
+            bcs::to_bytes(&self.pool)?,
+            bcs::to_bytes(&self.reward_a)?,
+            bcs::to_bytes(&self.reward_b)?,
+            bcs::to_bytes(&self.withdraw_fee)?,
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThalaStrategyData {
+    pub strategy_id: u8,
+    /// pool address -> position
+    pub pools: OrderedMap<AccountAddress, ThalaPosition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThalaPosition {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_amount: u128,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub asset: AccountAddress,
+    #[serde(deserialize_with = "deserialize_object_address")]
+    pub pair: AccountAddress,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub staked_lp_amount: u64,
+    pub reward_ids: Vec<String>,
+}
+
+pub struct Thala {
+    client: Arc<Client>,
+    extra_data: Option<ThalaExtraData>,
+    hook_data: Vec<u8>,
+}
+
+impl Thala {
+    pub fn new(client: Arc<Client>) -> Self {
+        Thala {
+            client,
+            extra_data: None,
+            hook_data: vec![],
+        }
+    }
+
+    pub fn with_extra_data(&mut self, extra_data: ThalaExtraData) -> &mut Self {
+        self.extra_data = Some(extra_data);
+
+        self
+    }
+
+    /// Emitted in a `vault::HookEvent` on withdraw
+    pub fn with_hook_data(&mut self, hook_data: Vec<u8>) -> &mut Self {
+        self.hook_data = hook_data;
+
+        self
+    }
+
+    fn packed_extra_data(&self) -> Result<Vec<Vec<u8>>> {
+        self.extra_data
+            .as_ref()
+            .ok_or_else(|| Error::Config("thala extra data is not set".into()))?
+            .pack()
+    }
+
+    pub async fn get_user_strategy_data(&self, wallet_id: WalletId) -> Result<ThalaStrategyData> {
+        let function = "get_user_strategy_data";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![bytes_arg(wallet_id.as_bytes())])
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+}
+
+#[async_trait]
+impl Strategy for Thala {
+    fn module_name(&self) -> String {
+        MODULE_NAME.to_string()
+    }
+
+    fn client(&self) -> Arc<Client> {
+        self.client.clone()
+    }
+
+    fn deposit_payload(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+    ) -> Result<EntryFunction> {
+        vault_deposit_payload(
+            &self.client,
+            self.strategy_id(),
+            wallet_id,
+            asset,
+            amount,
+            self.packed_extra_data()?,
+        )
+    }
+
+    fn withdraw_payload(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+        amount: u64,
+        gas_fee: u64,
+    ) -> Result<EntryFunction> {
+        let mut extra_data = self.packed_extra_data()?;
+        extra_data.push(self.hook_data.clone());
+
+        vault_withdraw_payload(
+            &self.client,
+            self.strategy_id(),
+            wallet_id,
+            asset,
+            amount,
+            gas_fee,
+            extra_data,
+        )
+    }
+
+    async fn get_account_state(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+    ) -> Result<AssetState> {
+        self.get_allocation_state(wallet_id, asset).await
+    }
+}
+
+#[async_trait]
+impl DexStrategy for Thala {
+    fn strategy_id(&self) -> StrategyID {
+        StrategyID::ThalaSwap
+    }
+
+    async fn get_positions(&self, wallet_id: WalletId) -> Result<Vec<DexPosition>> {
+        let data = self.get_user_strategy_data(wallet_id).await?;
+
+        Ok(data
+            .pools
+            .iter()
+            .map(|(_, position)| DexPosition {
+                asset: position.asset,
+                amount: position.amount,
+                pending_amount: 0,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_extra_data() {
+        let extra_data = ThalaExtraData {
+            pool: AccountAddress::from_hex_literal("0xb").unwrap(),
+            reward_a: "thl".to_string(),
+            reward_b: String::new(),
+            withdraw_fee: 100,
+        };
+        let packed = extra_data.pack().unwrap();
+
+        assert_eq!(packed.len(), 4);
+        assert_eq!(packed[0][31], 0xb);
+        assert_eq!(packed[1], vec![3, b't', b'h', b'l']);
+        assert_eq!(packed[2], vec![0]);
+        assert_eq!(packed[3], 100u64.to_le_bytes().to_vec());
+    }
+}
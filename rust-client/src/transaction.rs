@@ -0,0 +1,365 @@
+use aptos_sdk::{
+    move_types::account_address::AccountAddress,
+    rest_client::aptos_api_types::{Event, HashValue, TransactionInfo, UserTransaction},
+};
+pub use tx_options::{GasUnitPrice, TxOptions, TxOptionsError};
+
+use crate::{
+    Error, WalletId,
+    abort::{AbortReason, MoveAbort},
+    events::{
+        ClaimReferralFeeEvent, ConfigFeeEvent, ConfigureEvent, DepositedEvent,
+        DepositedToStrategyEvent, MoneyFiEvent, MoveEvent, RemoveAccountEvent, SetReferrerEvent,
+        ShareFeeEvent, SwapAssetsEvent, UpdateAccountEvent, UpsertAssetSupportedEvent,
+        VaultCreatedEvent, WalletAccountCreatedEvent, WithdrawFeeEvent, WithdrawnEvent,
+        WithdrawnFromStrategyEvent, find_event,
+    },
+};
+
+/// Outcome of simulating a transaction against the current ledger state
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    pub success: bool,
+    pub vm_status: String,
+    pub gas_used: u64,
+    pub gas_unit_price: u64,
+    /// MoneyFi events the transaction would emit, in emission order
+    pub events: Vec<MoneyFiEvent>,
+    /// MoneyFi events that failed to decode
+    pub undecoded: Vec<Event>,
+    /// Every event, including the ones of other modules
+    pub raw_events: Vec<Event>,
+    /// Set when the transaction would abort
+    pub abort: Option<MoveAbort>,
+}
+
+impl SimulationResult {
+    pub(crate) fn from_user_transaction(
+        txn: UserTransaction,
+        contract_address: AccountAddress,
+    ) -> Self {
+        let abort = if txn.info.success {
+            None
+        } else {
+            MoveAbort::from_vm_status(&txn.info.vm_status, contract_address)
+        };
+
+        let (events, undecoded) = MoneyFiEvent::decode_lossy(&txn.events, contract_address);
+
+        SimulationResult {
+            success: txn.info.success,
+            vm_status: txn.info.vm_status,
+            gas_used: txn.info.gas_used.0,
+            gas_unit_price: txn.request.gas_unit_price.0,
+            events,
+            undecoded,
+            raw_events: txn.events,
+            abort,
+        }
+    }
+
+    /// Named reason when the transaction would abort in a MoneyFi module
+    pub fn abort_reason(&self) -> Option<AbortReason> {
+        self.abort.as_ref().map(|abort| abort.reason)
+    }
+
+    /// Gas fee in octas
+    pub fn gas_fee(&self) -> u64 {
+        self.gas_used * self.gas_unit_price
+    }
+
+    /// Turns a failed simulation into the error the real submission would produce
+    pub fn into_result(self, contract_address: AccountAddress) -> Result<Self, Error> {
+        if self.success {
+            Ok(self)
+        } else {
+            Err(Error::from_vm_status(&self.vm_status, contract_address))
+        }
+    }
+}
+
+/// A committed transaction with the MoneyFi events it emitted, in emission order.
+/// Write calls fail with `Error::TransactionFailed` carrying the receipt when the
+/// transaction was committed but did not succeed.
+#[derive(Debug, Clone)]
+pub struct Receipt {
+    pub hash: HashValue,
+    pub version: u64,
+    pub gas_used: u64,
+    pub success: bool,
+    pub vm_status: String,
+    pub events: Vec<MoneyFiEvent>,
+    /// MoneyFi events that failed to decode, the transaction is committed regardless
+    pub undecoded: Vec<Event>,
+    /// Every event, including the ones of other modules
+    pub raw_events: Vec<Event>,
+    contract_address: AccountAddress,
+}
+
+impl Receipt {
+    pub(crate) fn new(
+        info: &TransactionInfo,
+        events: Vec<Event>,
+        contract_address: AccountAddress,
+    ) -> Self {
+        Self::from_events(
+            info.hash,
+            info.version.0,
+            info.gas_used.0,
+            info.success,
+            info.vm_status.clone(),
+            events,
+            contract_address,
+        )
+    }
+
+    fn from_events(
+        hash: HashValue,
+        version: u64,
+        gas_used: u64,
+        success: bool,
+        vm_status: String,
+        raw_events: Vec<Event>,
+        contract_address: AccountAddress,
+    ) -> Self {
+        let (events, undecoded) = MoneyFiEvent::decode_lossy(&raw_events, contract_address);
+
+        Receipt {
+            hash,
+            version,
+            gas_used,
+            success,
+            vm_status,
+            events,
+            undecoded,
+            raw_events,
+            contract_address,
+        }
+    }
+
+    /// Named reason when the transaction aborted in a MoneyFi module
+    pub fn abort_reason(&self) -> Option<AbortReason> {
+        MoveAbort::from_vm_status(&self.vm_status, self.contract_address).map(|abort| abort.reason)
+    }
+
+    /// Decodes the first `T` event, e.g. `receipt.event::<DepositedEvent>()` after a deposit
+    pub fn event<T: MoveEvent>(&self) -> Result<T, Error> {
+        find_event(&self.raw_events, self.contract_address)?.ok_or_else(|| {
+            Error::NotFound(format!(
+                "transaction {} did not emit {}::{}",
+                self.hash,
+                T::MODULE,
+                T::NAME
+            ))
+        })
+    }
+
+    pub fn deposited(&self) -> Vec<&DepositedEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::Deposited(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn withdrawn(&self) -> Vec<&WithdrawnEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::Withdrawn(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn deposited_to_strategy(&self) -> Vec<&DepositedToStrategyEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::DepositedToStrategy(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    /// Carries the `interest_amount` and `system_fee` of a strategy withdrawal
+    pub fn withdrawn_from_strategy(&self) -> Vec<&WithdrawnFromStrategyEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::WithdrawnFromStrategy(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn swap_assets(&self) -> Vec<&SwapAssetsEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::SwapAssets(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn share_fee(&self) -> Vec<&ShareFeeEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::ShareFee(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn claim_referral_fee(&self) -> Vec<&ClaimReferralFeeEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::ClaimReferralFee(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn withdraw_fee(&self) -> Vec<&WithdrawFeeEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::WithdrawFee(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn configure(&self) -> Vec<&ConfigureEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::Configure(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn upsert_asset_supported(&self) -> Vec<&UpsertAssetSupportedEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::UpsertAssetSupported(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn wallet_account_created(&self) -> Vec<&WalletAccountCreatedEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::WalletAccountCreated(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn config_fee(&self) -> Vec<&ConfigFeeEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::ConfigFee(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn set_referrer(&self) -> Vec<&SetReferrerEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::SetReferrer(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn vault_created(&self) -> Vec<&VaultCreatedEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::VaultCreated(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn update_account(&self) -> Vec<&UpdateAccountEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::UpdateAccount(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn remove_account(&self) -> Vec<&RemoveAccountEvent> {
+        self.filter_events(|event| match event {
+            MoneyFiEvent::RemoveAccount(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    /// Events of the given wallet, e.g. to pick one wallet out of a batched transaction
+    pub fn wallet_events(&self, wallet_id: WalletId) -> Vec<&MoneyFiEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.wallet_id() == Some(wallet_id))
+            .collect()
+    }
+
+    fn filter_events<'a, T>(&'a self, f: impl Fn(&'a MoneyFiEvent) -> Option<&'a T>) -> Vec<&'a T> {
+        self.events.iter().filter_map(f).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aptos_sdk::crypto::HashValue as CryptoHashValue;
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(typ: &str, data: serde_json::Value) -> Event {
+        serde_json::from_value(json!({
+            "guid": { "creation_number": "0", "account_address": "0x0" },
+            "sequence_number": "0",
+            "type": typ,
+            "data": data,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_receipt_events() {
+        let contract = AccountAddress::from_hex_literal("0xabc").unwrap();
+        let deposited = json!({
+            "sender": "0x1",
+            "wallet_account": { "inner": "0x2" },
+            "asset": { "inner": "0xa" },
+            "amount": "1000",
+            "lp_amount": "990",
+            "timestamp": "1700000000"
+        });
+        let receipt = Receipt::from_events(
+            CryptoHashValue::zero().into(),
+            7,
+            100,
+            true,
+            "Executed successfully".into(),
+            vec![
+                event("0x1::fungible_asset::Deposit", json!({ "amount": "1000" })),
+                event("0xabc::vault::DepositedEvent", deposited),
+                // fails to decode, the receipt is still returned
+                event("0xabc::vault::WithdrawnEvent", json!({ "amount": "1" })),
+            ],
+            contract,
+        );
+
+        assert_eq!(receipt.version, 7);
+        assert_eq!(receipt.raw_events.len(), 3);
+        assert_eq!(receipt.events.len(), 1);
+        assert_eq!(receipt.undecoded.len(), 1);
+        assert_eq!(receipt.deposited()[0].amount, 1000);
+        assert!(receipt.withdrawn().is_empty());
+        assert_eq!(receipt.event::<DepositedEvent>().unwrap().lp_amount, 990);
+        assert!(matches!(
+            receipt.event::<ConfigureEvent>(),
+            Err(Error::NotFound(_))
+        ));
+        assert!(receipt.event::<WithdrawnEvent>().is_err());
+    }
+
+    #[test]
+    fn test_failed_receipt() {
+        let contract = AccountAddress::from_hex_literal("0xabc").unwrap();
+        let vm_status = "Move abort in 0xabc::wallet_account: 0x80001";
+        let receipt = Receipt::from_events(
+            CryptoHashValue::zero().into(),
+            8,
+            50,
+            false,
+            vm_status.into(),
+            vec![],
+            contract,
+        );
+        assert!(receipt.abort_reason().is_some());
+
+        let err = Error::TransactionFailed {
+            receipt: Box::new(receipt),
+            error: Box::new(Error::from_vm_status(vm_status, contract)),
+        };
+        assert_eq!(err.receipt().unwrap().gas_used, 50);
+        assert_eq!(
+            err.abort_reason(),
+            err.receipt().and_then(Receipt::abort_reason)
+        );
+    }
+}
@@ -1,10 +1,14 @@
-use std::fmt::{self, Formatter};
+use std::{
+    fmt::{self, Formatter},
+    str::FromStr,
+};
 
-use serde::{Deserialize, Serialize};
+use aptos_sdk::{crypto::HashValue, move_types::account_address::AccountAddress};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
 
-use crate::utils::deserialize_string_from_hexstring;
+use crate::{Error, Result, utils::deserialize_string_from_hexstring};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TypeInfo {
     pub account_address: String,
     #[serde(deserialize_with = "deserialize_string_from_hexstring")]
@@ -22,3 +26,221 @@ impl fmt::Display for TypeInfo {
         )
     }
 }
+
+/// JSON form of a Move `OrderedMap<K, V>`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OrderedMap<K, V> {
+    pub entries: Vec<OrderedMapEntry<K, V>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OrderedMapEntry<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<K, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        OrderedMap { entries: vec![] }
+    }
+}
+
+impl<K: PartialEq, V> OrderedMap<K, V> {
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries
+            .iter()
+            .find(|entry| &entry.key == key)
+            .map(|entry| &entry.value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|entry| (&entry.key, &entry.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Identifies a wallet account, `wallet_account.move` expects 32 bytes but does not check it.
+/// Other lengths are rejected with `Error::InvalidWalletId`.
+/// Serialized as a `vector<u8>` in BCS and as a `0x` prefixed hex string in JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WalletId(pub [u8; 32]);
+
+impl WalletId {
+    pub const LENGTH: usize = 32;
+
+    /// Wallet id of an Aptos wallet, the address bytes
+    pub fn from_address(address: AccountAddress) -> Self {
+        WalletId(address.into_bytes())
+    }
+
+    /// Wallet id of a wallet on another chain, `sha3_256(chain_id || address)`.
+    /// `chain_id` is the internal chain id stored on the `WalletAccount`; the contract
+    /// stores the id it is given, so this must stay in sync with the verifier.
+    pub fn from_chain_address(chain_id: u8, address: &[u8]) -> Self {
+        let mut bytes = Vec::with_capacity(address.len() + 1);
+        bytes.push(chain_id);
+        bytes.extend_from_slice(address);
+
+        WalletId(HashValue::sha3_256_of(&bytes).to_vec().try_into().unwrap())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes = <[u8; 32]>::try_from(bytes).map_err(|_| Error::InvalidWalletId(bytes.len()))?;
+
+        Ok(WalletId(bytes))
+    }
+
+    /// Parses a hex string, with or without `0x`
+    pub fn from_hex(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s.strip_prefix("0x").unwrap_or(s))
+            .map_err(|e| Error::InvalidArgument(format!("invalid wallet id {}: {}", s, e)))?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_base58(s: &str) -> Result<Self> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|e| Error::InvalidArgument(format!("invalid wallet id {}: {}", s, e)))?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+
+    pub fn to_base58(&self) -> String {
+        bs58::encode(self.0).into_string()
+    }
+}
+
+impl fmt::Display for WalletId {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+/// Accepts `0x` prefixed hex, or base58 otherwise
+impl FromStr for WalletId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("0x") {
+            Self::from_hex(s)
+        } else {
+            Self::from_base58(s)
+        }
+    }
+}
+
+impl From<AccountAddress> for WalletId {
+    fn from(address: AccountAddress) -> Self {
+        Self::from_address(address)
+    }
+}
+
+impl From<[u8; 32]> for WalletId {
+    fn from(bytes: [u8; 32]) -> Self {
+        WalletId(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for WalletId {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Serialize for WalletId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_hex())
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WalletId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            WalletId::from_hex(&s).map_err(D::Error::custom)
+        } else {
+            let bytes = Vec::<u8>::deserialize(deserializer)?;
+            WalletId::from_bytes(&bytes).map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aptos_sdk::bcs;
+
+    use super::*;
+
+    #[test]
+    fn test_wallet_id_encoding() {
+        let wallet_id = WalletId([0xab; 32]);
+
+        assert_eq!(wallet_id.to_hex().parse::<WalletId>().unwrap(), wallet_id);
+        assert_eq!(
+            wallet_id.to_base58().parse::<WalletId>().unwrap(),
+            wallet_id
+        );
+        assert!("0x0102".parse::<WalletId>().is_err());
+        assert!("wallet_1".parse::<WalletId>().is_err());
+
+        // same bytes as a Move `vector<u8>` argument
+        assert_eq!(
+            bcs::to_bytes(&wallet_id).unwrap(),
+            bcs::to_bytes(&wallet_id.to_vec()).unwrap()
+        );
+        assert_eq!(
+            bcs::from_bytes::<WalletId>(&bcs::to_bytes(&wallet_id).unwrap()).unwrap(),
+            wallet_id
+        );
+        assert_eq!(
+            serde_json::to_value(wallet_id).unwrap(),
+            serde_json::json!(wallet_id.to_hex())
+        );
+
+        assert_eq!(
+            WalletId::from_address(AccountAddress::ONE).as_bytes()[31],
+            1
+        );
+        assert_eq!(
+            WalletId::from_chain_address(1, &[0x11; 20]).to_hex(),
+            "0xd679ae93bafe5bd7d62d21d0751fdce2b9e389be6a31e8aa4d417f65c81db17e"
+        );
+        assert_ne!(
+            WalletId::from_chain_address(1, &[0x11; 20]),
+            WalletId::from_chain_address(2, &[0x11; 20])
+        );
+
+        assert!(matches!(
+            WalletId::from_bytes(&[1, 2]),
+            Err(Error::InvalidWalletId(2))
+        ));
+        let err = serde_json::from_value::<WalletId>(serde_json::json!("0x0102")).unwrap_err();
+        assert!(err.to_string().contains("32 bytes, got 2"));
+    }
+}
@@ -1,23 +1,30 @@
-use aptos_sdk::types::chain_id::NamedChain;
-use serde::{Deserialize, Deserializer};
+use std::{fmt::Display, str::FromStr};
 
-pub fn get_indexer_api_endpoint(chain: NamedChain) -> &'static str {
+use aptos_sdk::{move_types::account_address::AccountAddress, types::chain_id::NamedChain};
+use serde::{Deserialize, Deserializer, de::Error as _};
+use serde_json::Value;
+
+use crate::{Error, OrderedMap, OrderedMapEntry, Result, WalletId};
+
+/// Returns `None` for networks without a public endpoint
+pub fn get_indexer_api_endpoint(chain: NamedChain) -> Option<&'static str> {
     match chain {
-        NamedChain::MAINNET => "https://api.mainnet.aptoslabs.com/v1/graphql",
-        NamedChain::TESTNET => "https://api.testnet.aptoslabs.com/v1/graphql",
-        NamedChain::DEVNET => "https://api.devnet.aptoslabs.com/v1/graphql",
-        NamedChain::TESTING => "http://127.0.0.1:8080/v1/graphql",
-        NamedChain::PREMAINNET => todo!(),
+        NamedChain::MAINNET => Some("https://api.mainnet.aptoslabs.com/v1/graphql"),
+        NamedChain::TESTNET => Some("https://api.testnet.aptoslabs.com/v1/graphql"),
+        NamedChain::DEVNET => Some("https://api.devnet.aptoslabs.com/v1/graphql"),
+        NamedChain::TESTING => Some("http://127.0.0.1:8080/v1/graphql"),
+        NamedChain::PREMAINNET => None,
     }
 }
 
-pub fn get_rest_api_endpoint(chain: NamedChain) -> &'static str {
+/// Returns `None` for networks without a public endpoint
+pub fn get_rest_api_endpoint(chain: NamedChain) -> Option<&'static str> {
     match chain {
-        NamedChain::MAINNET => "https://api.mainnet.aptoslabs.com/v1",
-        NamedChain::TESTNET => "https://api.testnet.aptoslabs.com/v1",
-        NamedChain::DEVNET => "https://api.devnet.aptoslabs.com/v1",
-        NamedChain::TESTING => "http://127.0.0.1:8080/v1/",
-        NamedChain::PREMAINNET => todo!(),
+        NamedChain::MAINNET => Some("https://api.mainnet.aptoslabs.com/v1"),
+        NamedChain::TESTNET => Some("https://api.testnet.aptoslabs.com/v1"),
+        NamedChain::DEVNET => Some("https://api.devnet.aptoslabs.com/v1"),
+        NamedChain::TESTING => Some("http://127.0.0.1:8080/v1/"),
+        NamedChain::PREMAINNET => None,
     }
 }
 
@@ -33,3 +40,223 @@ pub fn hex_to_string(val: String) -> Option<String> {
     let decoded = hex::decode(val.strip_prefix("0x").unwrap_or(&*val)).ok()?;
     String::from_utf8(decoded).ok()
 }
+
+/// Move integers wider than 32 bits are encoded as decimal strings in JSON
+pub fn deserialize_number_from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(serde_json::Number),
+    }
+
+    let s = match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(s) => s,
+        StringOrNumber::Number(n) => n.to_string(),
+    };
+    s.parse::<T>().map_err(D::Error::custom)
+}
+
+/// Same as `deserialize_number_from_string` for a Move vector of integers
+pub fn deserialize_numbers_from_strings<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Vec::<Value>::deserialize(deserializer)?
+        .iter()
+        .map(|value| parse_number(value).map_err(D::Error::custom))
+        .collect()
+}
+
+/// Same as `deserialize_number_from_string` for the values of a Move `OrderedMap<K, u64>`
+pub fn deserialize_number_map<'de, D, K, T>(deserializer: D) -> Result<OrderedMap<K, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Deserialize<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let map = OrderedMap::<K, Value>::deserialize(deserializer)?;
+    let entries = map
+        .entries
+        .into_iter()
+        .map(|OrderedMapEntry { key, value }| {
+            let value = parse_number(&value).map_err(D::Error::custom)?;
+
+            Ok(OrderedMapEntry { key, value })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(OrderedMap { entries })
+}
+
+/// Decodes a Move `Object<T>`, encoded as `{ "inner": "0x..." }`
+pub fn deserialize_object_address<'de, D>(deserializer: D) -> Result<AccountAddress, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct ObjectJson {
+        inner: AccountAddress,
+    }
+
+    Ok(ObjectJson::deserialize(deserializer)?.inner)
+}
+
+/// Decodes a Move `vector<u8>`, encoded as a `0x` prefixed hex string
+pub fn deserialize_bytes_from_hexstring<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = <String>::deserialize(deserializer)?;
+    hex::decode(s.strip_prefix("0x").unwrap_or(&s)).map_err(D::Error::custom)
+}
+
+/// Decodes a Move `Option<T>`, encoded as `{ "vec": [] }` or `{ "vec": [value] }`
+pub fn deserialize_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    struct OptionJson<T> {
+        vec: Vec<T>,
+    }
+
+    Ok(OptionJson::deserialize(deserializer)?.vec.into_iter().next())
+}
+
+/// Same as `deserialize_option` for a Move `Option` of a wide integer
+pub fn deserialize_option_number_from_string<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    deserialize_option::<D, Value>(deserializer)?
+        .map(|value| parse_number(&value).map_err(D::Error::custom))
+        .transpose()
+}
+
+/// Decodes a wallet id `vector<u8>` where an empty vector means none, e.g. a referrer
+pub fn deserialize_optional_wallet_id<'de, D>(
+    deserializer: D,
+) -> Result<Option<WalletId>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = deserialize_bytes_from_hexstring(deserializer)?;
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+
+    WalletId::from_bytes(&bytes).map(Some).map_err(D::Error::custom)
+}
+
+/// Encodes bytes as a `vector<u8>` view function argument
+pub(crate) fn bytes_arg(bytes: &[u8]) -> Value {
+    Value::String(format!("0x{}", hex::encode(bytes)))
+}
+
+pub(crate) fn expect_len(function: &str, data: &[Value], len: usize) -> Result<()> {
+    if data.len() != len {
+        return Err(Error::Decode(format!(
+            "{} returned {} values, expected {}",
+            function,
+            data.len(),
+            len
+        )));
+    }
+
+    Ok(())
+}
+
+/// Parses a view function integer, which may be a JSON string or number
+pub(crate) fn parse_number<T>(value: &Value) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let s = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        _ => return Err(Error::Decode(format!("expected a number, got {}", value))),
+    };
+
+    s.parse::<T>()
+        .map_err(|e| Error::Decode(format!("invalid number {}: {}", s, e)))
+}
+
+/// Ledger state headers the Aptos REST client expects on every node response
+#[cfg(test)]
+const LEDGER_HEADERS: &str = "x-aptos-chain-id: 4\r\nx-aptos-ledger-version: 1\r\n\
+    x-aptos-ledger-oldest-version: 0\r\nx-aptos-ledger-timestampusec: 0\r\n\
+    x-aptos-epoch: 1\r\nx-aptos-block-height: 1\r\nx-aptos-oldest-block-height: 0\r\n";
+
+/// Local HTTP server answering one request per connection with the next of `responses`.
+/// The handle returns the JSON request bodies, `null` for a GET, once every response was sent.
+#[cfg(test)]
+pub(crate) async fn serve_json(
+    responses: Vec<Value>,
+) -> (url::Url, tokio::task::JoinHandle<Vec<Value>>) {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = url::Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+    let handle = tokio::spawn(async move {
+        let mut requests = vec![];
+        for response in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0u8; 4096];
+            let body = loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                assert!(n > 0, "connection closed before the body");
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                let Some((headers, body)) = text.split_once("\r\n\r\n") else {
+                    continue;
+                };
+                let length = headers
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or_default();
+                if body.len() >= length {
+                    break body.to_string();
+                }
+            };
+
+            let response = response.to_string();
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                 {}content-length: {}\r\nconnection: close\r\n\r\n{}",
+                LEDGER_HEADERS,
+                response.len(),
+                response
+            );
+            stream.write_all(reply.as_bytes()).await.unwrap();
+            requests.push(serde_json::from_str(&body).unwrap_or(Value::Null));
+        }
+
+        requests
+    });
+
+    (url, handle)
+}
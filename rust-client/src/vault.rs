@@ -0,0 +1,461 @@
+use aptos_sdk::{
+    bcs,
+    move_types::account_address::AccountAddress,
+    types::{account_address::create_object_address, transaction::EntryFunction},
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    Client, Error, OrderedMap, OrderedMapEntry, Receipt, Result, SimulationResult, TxOptions,
+    TypeInfo, WalletId,
+    admin::{AssetConfig, VaultConfig},
+    events::{DepositedEvent, WithdrawnEvent},
+    utils::{
+        bytes_arg, deserialize_number_map, deserialize_object_address, expect_len, parse_number,
+    },
+};
+
+const MODULE_NAME: &str = "vault";
+const VAULT_SEED: &[u8] = b"vault::VAULT";
+
+/// Totals of an asset held by the vault
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultAsset {
+    pub asset: AccountAddress,
+    pub total_amount: u128,
+    pub total_lp_amount: u128,
+    pub total_distributed_amount: u128,
+}
+
+/// Entry of `get_assets`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultAssetTotal {
+    pub asset: AccountAddress,
+    pub total_amount: u128,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssetFee {
+    pub total_fee: u64,
+    pub pending_fee: u64,
+}
+
+/// The `vault::Config` resource
+#[derive(Debug, Clone, Deserialize)]
+pub struct VaultSettings {
+    #[serde(flatten)]
+    pub config: VaultConfig,
+    pub supported_assets: OrderedMap<AccountAddress, AssetConfig>,
+}
+
+impl VaultSettings {
+    /// Same rules as `vault::can_deposit`
+    pub fn check_deposit(&self, asset: AccountAddress, amount: u64) -> Result<()> {
+        if !self.config.enable_deposit {
+            return Err(Error::NotAllowed("deposits are disabled".into()));
+        }
+        let config = self.enabled_asset_config(asset)?;
+
+        check_limits("deposit", amount, config.min_deposit, config.max_deposit)
+    }
+
+    /// Same rules as `vault::can_withdraw`, `amount` is the one from `capped_withdraw_amount`
+    pub fn check_withdraw(&self, asset: AccountAddress, amount: u64) -> Result<()> {
+        if !self.config.enable_withdraw {
+            return Err(Error::NotAllowed("withdrawals are disabled".into()));
+        }
+        let config = self.enabled_asset_config(asset)?;
+
+        check_limits("withdraw", amount, config.min_withdraw, config.max_withdraw)
+    }
+
+    /// Amount `vault::withdraw` checks and transfers: the requested amount capped to the
+    /// wallet account balance, plus the pending referral fee when it is claimed first
+    pub fn capped_withdraw_amount(amount: u64, balance: u64, pending_referral_fee: u64) -> u64 {
+        let claimed = if pending_referral_fee > 0 && amount >= pending_referral_fee {
+            pending_referral_fee
+        } else {
+            0
+        };
+
+        amount.min(balance.saturating_add(claimed))
+    }
+
+    fn enabled_asset_config(&self, asset: AccountAddress) -> Result<&AssetConfig> {
+        match self.supported_assets.get(&asset) {
+            Some(config) if config.enabled => Ok(config),
+            Some(_) => Err(Error::NotAllowed(format!("asset {} is disabled", asset))),
+            None => Err(Error::NotAllowed(format!(
+                "asset {} is not supported",
+                asset
+            ))),
+        }
+    }
+}
+
+/// A min or max of 0 means no limit
+fn check_limits(action: &str, amount: u64, min: u64, max: u64) -> Result<()> {
+    if amount == 0 {
+        return Err(Error::InvalidArgument(format!("{} amount is 0", action)));
+    }
+    if min > 0 && amount < min {
+        return Err(Error::InvalidArgument(format!(
+            "{} amount {} is below the minimum {}",
+            action, amount, min
+        )));
+    }
+    if max > 0 && amount > max {
+        return Err(Error::InvalidArgument(format!(
+            "{} amount {} is above the maximum {}",
+            action, amount, max
+        )));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct StorageResource {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    object: AccountAddress,
+}
+
+#[derive(Debug, Deserialize)]
+struct LPTokenResource {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    token: AccountAddress,
+}
+
+impl Client {
+    pub async fn get_vault_settings(&self) -> Result<VaultSettings> {
+        let data = self
+            .resource(self.contract_address, MODULE_NAME, "Config")
+            .await?;
+
+        Ok(serde_json::from_value(data)?)
+    }
+
+    /// Deposits from the connected wallet, which must have a wallet account
+    pub fn deposit_payload(&self, asset: AccountAddress, amount: u64) -> Result<EntryFunction> {
+        self.entry_function(
+            MODULE_NAME,
+            "deposit",
+            vec![bcs::to_bytes(&asset)?, bcs::to_bytes(&amount)?],
+        )
+    }
+
+    /// Checks the vault config, then deposits. The event has the minted LP amount.
+    pub async fn deposit(
+        &self,
+        asset: AccountAddress,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<(Receipt, DepositedEvent)> {
+        self.get_vault_settings()
+            .await?
+            .check_deposit(asset, amount)?;
+        let efn = self.deposit_payload(asset, amount)?;
+
+        self.send_for_event(efn, opts).await
+    }
+
+    pub async fn simulate_deposit(
+        &self,
+        asset: AccountAddress,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.deposit_payload(asset, amount)?;
+
+        self.simulate(efn, opts).await
+    }
+
+    /// Withdraws to the connected wallet. Pending referral fees are claimed first.
+    pub fn withdraw_payload(&self, asset: AccountAddress, amount: u64) -> Result<EntryFunction> {
+        self.entry_function(
+            MODULE_NAME,
+            "withdraw",
+            vec![bcs::to_bytes(&asset)?, bcs::to_bytes(&amount)?],
+        )
+    }
+
+    /// Checks the vault config against the amount the contract will withdraw, then
+    /// withdraws. `u64::MAX` withdraws everything. The event has the burned LP amount.
+    pub async fn withdraw(
+        &self,
+        asset: AccountAddress,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<(Receipt, WithdrawnEvent)> {
+        let owner = self.signer_address().ok_or(Error::NoAccount)?;
+        let wallet_accounts = self.wallet_accounts();
+        let account = wallet_accounts.get_wallet_account_by_owner(owner).await?;
+        let wallet_id = wallet_accounts.get_wallet_id(account).await?;
+        let pending_referral_fee = self
+            .get_pending_referral_fees(wallet_id)
+            .await?
+            .get(&asset)
+            .copied()
+            .unwrap_or_default();
+        let balance = self.get_primary_balance(account, asset).await?;
+
+        self.get_vault_settings().await?.check_withdraw(
+            asset,
+            VaultSettings::capped_withdraw_amount(amount, balance, pending_referral_fee),
+        )?;
+        let efn = self.withdraw_payload(asset, amount)?;
+
+        self.send_for_event(efn, opts).await
+    }
+
+    pub async fn simulate_withdraw(
+        &self,
+        asset: AccountAddress,
+        amount: u64,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.withdraw_payload(asset, amount)?;
+
+        self.simulate(efn, opts).await
+    }
+
+    /// Returns every asset of the vault and its total amount, see `get_asset` for the rest
+    pub async fn get_assets(&self) -> Result<Vec<VaultAssetTotal>> {
+        let function = "get_assets";
+        let data = self.view(MODULE_NAME, function, vec![]).await?;
+        expect_len(function, &data, 2)?;
+
+        let assets = serde_json::from_value::<Vec<AccountAddress>>(data[0].clone())?;
+        let amounts = serde_json::from_value::<Vec<Value>>(data[1].clone())?
+            .iter()
+            .map(parse_number::<u128>)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(assets
+            .into_iter()
+            .zip(amounts)
+            .map(|(asset, total_amount)| VaultAssetTotal {
+                asset,
+                total_amount,
+            })
+            .collect())
+    }
+
+    pub async fn get_asset(&self, asset: AccountAddress) -> Result<VaultAsset> {
+        let function = "get_asset";
+        let data = self
+            .view(MODULE_NAME, function, vec![serde_json::to_value(asset)?])
+            .await?;
+        expect_len(function, &data, 3)?;
+
+        Ok(VaultAsset {
+            asset,
+            total_amount: parse_number(&data[0])?,
+            total_lp_amount: parse_number(&data[1])?,
+            total_distributed_amount: parse_number(&data[2])?,
+        })
+    }
+
+    pub async fn get_fee(&self, asset: AccountAddress) -> Result<AssetFee> {
+        let function = "get_fee";
+        let data = self
+            .view(MODULE_NAME, function, vec![serde_json::to_value(asset)?])
+            .await?;
+        expect_len(function, &data, 2)?;
+
+        Ok(AssetFee {
+            total_fee: parse_number(&data[0])?,
+            pending_fee: parse_number(&data[1])?,
+        })
+    }
+
+    pub async fn get_supported_assets(&self) -> Result<Vec<AccountAddress>> {
+        let function = "get_supported_assets";
+        let data = self.view(MODULE_NAME, function, vec![]).await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+
+    /// Returns strategy type -> strategy deposit address
+    pub async fn get_strategy_registry(&self) -> Result<OrderedMap<TypeInfo, AccountAddress>> {
+        let function = "get_strategy_registry";
+        let data = self.view(MODULE_NAME, function, vec![]).await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+
+    /// Returns asset -> referral fee not yet claimed by the wallet, zero amounts are omitted
+    pub async fn get_pending_referral_fees(
+        &self,
+        wallet_id: WalletId,
+    ) -> Result<OrderedMap<AccountAddress, u64>> {
+        let function = "get_pending_referral_fees";
+        let data = self
+            .view(MODULE_NAME, function, vec![bytes_arg(wallet_id.as_bytes())])
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(deserialize_number_map(data[0].clone())?)
+    }
+
+    /// Address of the LP token metadata.
+    /// `vault::get_lp_token` is not a view, the resource is read instead.
+    pub async fn get_lp_token(&self) -> Result<AccountAddress> {
+        let data = self
+            .resource(self.contract_address, MODULE_NAME, "LPToken")
+            .await?;
+
+        Ok(serde_json::from_value::<LPTokenResource>(data)?.token)
+    }
+
+    /// Balance of `asset` in the primary store of `owner`
+    async fn get_primary_balance(
+        &self,
+        owner: AccountAddress,
+        asset: AccountAddress,
+    ) -> Result<u64> {
+        let function = "balance";
+        let data = self
+            .view_generic_at(
+                AccountAddress::ONE,
+                "primary_fungible_store",
+                function,
+                &["0x1::fungible_asset::Metadata"],
+                vec![serde_json::to_value(owner)?, serde_json::to_value(asset)?],
+            )
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        parse_number(&data[0])
+    }
+
+    /// Address of the object owning the MoneyFi child objects
+    pub async fn get_storage_address(&self) -> Result<AccountAddress> {
+        let data = self
+            .resource(self.contract_address, "storage", "Storage")
+            .await?;
+
+        Ok(serde_json::from_value::<StorageResource>(data)?.object)
+    }
+
+    /// Address holding the vault assets, derived the same way as `vault::get_vault_address`
+    pub async fn get_vault_address(&self) -> Result<AccountAddress> {
+        Ok(create_object_address(
+            self.get_storage_address().await?,
+            VAULT_SEED,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_deposit_and_withdraw() {
+        let asset = AccountAddress::from_hex_literal("0xa").unwrap();
+        let mut settings = VaultSettings {
+            config: VaultConfig {
+                enable_deposit: true,
+                enable_withdraw: false,
+                system_fee_percent: 2000,
+                fee_recipient: AccountAddress::ONE,
+                referral_percents: vec![],
+            },
+            supported_assets: OrderedMap {
+                entries: vec![OrderedMapEntry {
+                    key: asset,
+                    value: AssetConfig {
+                        enabled: true,
+                        min_deposit: 100,
+                        max_deposit: 0,
+                        ..Default::default()
+                    },
+                }],
+            },
+        };
+
+        assert!(settings.check_deposit(asset, 100).is_ok());
+        assert!(settings.check_deposit(asset, u64::MAX).is_ok());
+        assert!(settings.check_deposit(asset, 99).is_err());
+        assert!(settings.check_deposit(AccountAddress::ONE, 100).is_err());
+        assert!(matches!(
+            settings.check_withdraw(asset, 100),
+            Err(Error::NotAllowed(_))
+        ));
+
+        settings.supported_assets.entries[0].value.enabled = false;
+        assert!(settings.check_deposit(asset, 100).is_err());
+    }
+
+    #[test]
+    fn test_check_capped_withdraw() {
+        let asset = AccountAddress::from_hex_literal("0xa").unwrap();
+        let settings = VaultSettings {
+            config: VaultConfig {
+                enable_deposit: true,
+                enable_withdraw: true,
+                system_fee_percent: 2000,
+                fee_recipient: AccountAddress::ONE,
+                referral_percents: vec![],
+            },
+            supported_assets: OrderedMap {
+                entries: vec![OrderedMapEntry {
+                    key: asset,
+                    value: AssetConfig {
+                        enabled: true,
+                        min_withdraw: 10,
+                        max_withdraw: 1000,
+                        ..Default::default()
+                    },
+                }],
+            },
+        };
+        let check = |amount, balance, pending_referral_fee| {
+            settings.check_withdraw(
+                asset,
+                VaultSettings::capped_withdraw_amount(amount, balance, pending_referral_fee),
+            )
+        };
+
+        // withdraw all
+        assert!(check(u64::MAX, 500, 0).is_ok());
+        assert!(check(2000, 800, 0).is_ok());
+        // the claimed referral fee is withdrawn too
+        assert!(check(2000, 800, 300).is_err());
+        assert_eq!(VaultSettings::capped_withdraw_amount(100, 50, 200), 50);
+        assert!(check(2000, 5, 0).is_err());
+        assert!(check(2000, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_settings() {
+        let settings = serde_json::from_value::<VaultSettings>(serde_json::json!({
+            "enable_deposit": true,
+            "enable_withdraw": true,
+            "system_fee_percent": "2000",
+            "fee_recipient": "0x1",
+            "referral_percents": ["500", "300"],
+            "supported_assets": {
+                "entries": [{
+                    "key": "0xa",
+                    "value": {
+                        "enabled": true,
+                        "min_deposit": "1",
+                        "max_deposit": "0",
+                        "min_withdraw": "1",
+                        "max_withdraw": "0",
+                        "lp_exchange_rate": "1000000000000"
+                    }
+                }]
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(settings.config.referral_percents, vec![500, 300]);
+        assert_eq!(settings.supported_assets.len(), 1);
+    }
+}
@@ -0,0 +1,502 @@
+use std::sync::Arc;
+
+use aptos_sdk::{
+    bcs,
+    move_types::account_address::AccountAddress,
+    rest_client::aptos_api_types::U64,
+    types::{account_address::create_object_address, transaction::EntryFunction},
+};
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+
+use crate::{
+    Client, Error, OrderedMap, OrderedMapEntry, Receipt, Result, SimulationResult,
+    TransactionSigner, TxOptions, WalletId,
+    access_control::Role,
+    admin::{MAX_FEE_PERCENT, validate_referral_percents},
+    events::WalletAccountCreatedEvent,
+    utils::{
+        bytes_arg, deserialize_number_from_string, deserialize_numbers_from_strings,
+        deserialize_object_address, deserialize_option, deserialize_option_number_from_string,
+        deserialize_optional_wallet_id, expect_len,
+    },
+};
+
+const MODULE_NAME: &str = "wallet_account";
+const WALLET_ACCOUNT_SEED: &[u8] = b"wallet_account::WALLET_ACCOUNT";
+
+/// The `wallet_account::WalletAccount` resource
+#[derive(Debug, Clone, Deserialize)]
+pub struct WalletAccount {
+    pub wallet_id: WalletId,
+    /// Internal chain id, 0 for Aptos
+    pub chain_id: u8,
+    /// Owner of the account, only set for Aptos wallets
+    #[serde(deserialize_with = "deserialize_option")]
+    pub wallet_address: Option<AccountAddress>,
+    #[serde(deserialize_with = "deserialize_optional_wallet_id")]
+    pub referrer_wallet_id: Option<WalletId>,
+    pub assets: OrderedMap<AccountAddress, AccountAsset>,
+    #[serde(flatten)]
+    pub fee_config: FeeConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct AccountAsset {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub current_amount: u64,
+    /// Accumulated deposited amount
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub deposited_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lp_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub swap_out_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub swap_in_amount: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub distributed_amount: u64,
+    /// Accumulated withdrawn amount
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub withdrawn_amount: u64,
+    /// Accumulated gross interest
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interest_amount: u64,
+    /// Accumulated interest shared as fees
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interest_share_amount: u64,
+    /// reward token -> amount
+    pub rewards: OrderedMap<AccountAddress, U64>,
+}
+
+impl AccountAsset {
+    pub fn net_interest_amount(&self) -> u64 {
+        self.interest_amount
+            .saturating_sub(self.interest_share_amount)
+    }
+}
+
+/// Per-account fee overrides, arguments of `wallet_account::config_fee`.
+/// Unset or empty values fall back to the vault config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct FeeConfig {
+    #[serde(deserialize_with = "deserialize_option_number_from_string")]
+    pub system_fee_percent: Option<u64>,
+    /// [level_1, level_2, level_3, ...]
+    #[serde(deserialize_with = "deserialize_numbers_from_strings")]
+    pub referral_percents: Vec<u64>,
+}
+
+impl FeeConfig {
+    /// Checks the invariants asserted by `wallet_account::config_fee`
+    pub fn validate(&self) -> Result<()> {
+        if let Some(percent) = self
+            .system_fee_percent
+            .filter(|percent| *percent > MAX_FEE_PERCENT)
+        {
+            return Err(Error::InvalidArgument(format!(
+                "system fee percent {} exceeds {}",
+                percent, MAX_FEE_PERCENT
+            )));
+        }
+
+        validate_referral_percents(&self.referral_percents)
+    }
+}
+
+/// A level of the referral chain of a wallet account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Referrer {
+    pub wallet_account: AccountAddress,
+    /// The referrer's own percent for this level, if configured
+    pub share_percent: Option<u64>,
+}
+
+/// `wallet_account.move` stores no referrer as an empty vector
+fn referrer_bytes(referrer_wallet_id: Option<WalletId>) -> Vec<u8> {
+    referrer_wallet_id
+        .map(|wallet_id| wallet_id.to_vec())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Deserialize)]
+struct WalletAccountObject {
+    #[serde(deserialize_with = "deserialize_object_address")]
+    wallet_account: AccountAddress,
+}
+
+/// Derives the wallet account object address the same way as
+/// `wallet_account::get_wallet_account_object_address`
+pub fn wallet_account_address(
+    storage_address: AccountAddress,
+    wallet_id: WalletId,
+) -> AccountAddress {
+    // bcs::to_bytes(&vector[WALLET_ACCOUNT_SEED, wallet_id])
+    let seed = bcs::to_bytes(&vec![WALLET_ACCOUNT_SEED, wallet_id.as_bytes()])
+        .expect("byte vectors are always serializable");
+
+    create_object_address(storage_address, &seed)
+}
+
+/// Wallet account registration, configuration and readers.
+/// Accounts are addressed by wallet id, their object address is derived locally
+/// once the storage address is known.
+pub struct WalletAccounts {
+    client: Arc<Client>,
+    storage_address: OnceCell<AccountAddress>,
+}
+
+impl WalletAccounts {
+    pub fn new(client: Arc<Client>) -> Self {
+        WalletAccounts {
+            client,
+            storage_address: OnceCell::new(),
+        }
+    }
+
+    /// Skips fetching the `storage::Storage` resource on first use
+    pub fn with_storage_address(&mut self, storage_address: AccountAddress) -> &mut Self {
+        self.storage_address = OnceCell::new_with(Some(storage_address));
+
+        self
+    }
+
+    pub async fn storage_address(&self) -> Result<AccountAddress> {
+        self.storage_address
+            .get_or_try_init(|| self.client.get_storage_address())
+            .await
+            .copied()
+    }
+
+    pub async fn wallet_account_address(&self, wallet_id: WalletId) -> Result<AccountAddress> {
+        Ok(wallet_account_address(
+            self.storage_address().await?,
+            wallet_id,
+        ))
+    }
+
+    /// The connected signer registers its own wallet account, `verifier` must be a service account
+    pub fn register_payload(
+        &self,
+        wallet_id: WalletId,
+        referrer_wallet_id: Option<WalletId>,
+    ) -> Result<EntryFunction> {
+        if referrer_wallet_id == Some(wallet_id) {
+            return Err(Error::InvalidArgument(
+                "wallet id and referrer wallet id are the same".into(),
+            ));
+        }
+
+        self.client.entry_function(
+            MODULE_NAME,
+            "register",
+            vec![
+                bcs::to_bytes(&wallet_id)?,
+                bcs::to_bytes(&referrer_bytes(referrer_wallet_id))?,
+            ],
+        )
+    }
+
+    /// Submits `register` as a multi-agent transaction co-signed by `verifier`
+    pub async fn register(
+        &self,
+        wallet_id: WalletId,
+        referrer_wallet_id: Option<WalletId>,
+        verifier: Arc<dyn TransactionSigner>,
+        opts: Option<TxOptions>,
+    ) -> Result<(Receipt, WalletAccountCreatedEvent)> {
+        let efn = self.register_payload(wallet_id, referrer_wallet_id)?;
+        self.client
+            .access_control()
+            .check_role(verifier.address(), Role::ServiceAccount)
+            .await?;
+        let pending_txn = self
+            .client
+            .submit_multi_agent(efn, &[verifier], opts)
+            .await?;
+
+        self.client.wait_for_event(&pending_txn).await
+    }
+
+    /// Sets the referrer of the connected signer's wallet account, only once
+    pub fn set_referrer_payload(&self, referrer_wallet_id: WalletId) -> Result<EntryFunction> {
+        self.client.entry_function(
+            MODULE_NAME,
+            "set_referrer",
+            vec![bcs::to_bytes(&referrer_wallet_id)?],
+        )
+    }
+
+    pub async fn set_referrer(
+        &self,
+        referrer_wallet_id: WalletId,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let efn = self.set_referrer_payload(referrer_wallet_id)?;
+
+        self.client.send_tx(efn, opts).await
+    }
+
+    pub async fn simulate_set_referrer(
+        &self,
+        referrer_wallet_id: WalletId,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.set_referrer_payload(referrer_wallet_id)?;
+
+        self.client.simulate(efn, opts).await
+    }
+
+    /// Requires the fee manager role
+    pub async fn config_fee_payload(
+        &self,
+        wallet_id: WalletId,
+        config: &FeeConfig,
+    ) -> Result<EntryFunction> {
+        config.validate()?;
+        let account = self.wallet_account_address(wallet_id).await?;
+
+        self.client.entry_function(
+            MODULE_NAME,
+            "config_fee",
+            vec![
+                bcs::to_bytes(&account)?,
+                bcs::to_bytes(&config.system_fee_percent)?,
+                bcs::to_bytes(&config.referral_percents)?,
+            ],
+        )
+    }
+
+    pub async fn config_fee(
+        &self,
+        wallet_id: WalletId,
+        config: &FeeConfig,
+        opts: Option<TxOptions>,
+    ) -> Result<Receipt> {
+        let efn = self.config_fee_payload(wallet_id, config).await?;
+        self.client
+            .send_restricted_tx(efn, &[Role::FeeManager], opts)
+            .await
+    }
+
+    pub async fn simulate_config_fee(
+        &self,
+        wallet_id: WalletId,
+        config: &FeeConfig,
+        opts: Option<TxOptions>,
+    ) -> Result<SimulationResult> {
+        let efn = self.config_fee_payload(wallet_id, config).await?;
+
+        self.client.simulate(efn, opts).await
+    }
+
+    pub async fn has_wallet_account(&self, wallet_id: WalletId) -> Result<bool> {
+        let function = "has_wallet_account";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![bytes_arg(wallet_id.as_bytes())])
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+
+    pub async fn get_wallet_account(&self, wallet_id: WalletId) -> Result<WalletAccount> {
+        let address = self.wallet_account_address(wallet_id).await?;
+
+        self.get_wallet_account_at(address).await
+    }
+
+    pub async fn get_wallet_account_at(&self, address: AccountAddress) -> Result<WalletAccount> {
+        let data = self
+            .client
+            .resource(address, MODULE_NAME, "WalletAccount")
+            .await?;
+
+        Ok(serde_json::from_value(data)?)
+    }
+
+    /// Returns the wallet account object registered by an Aptos wallet
+    pub async fn get_wallet_account_by_owner(
+        &self,
+        owner: AccountAddress,
+    ) -> Result<AccountAddress> {
+        let data = self
+            .client
+            .resource(owner, MODULE_NAME, "WalletAccountObject")
+            .await?;
+
+        Ok(serde_json::from_value::<WalletAccountObject>(data)?.wallet_account)
+    }
+
+    pub async fn get_wallet_id(&self, wallet_account: AccountAddress) -> Result<WalletId> {
+        let function = "get_wallet_id_by_wallet_account";
+        let data = self
+            .client
+            .view(
+                MODULE_NAME,
+                function,
+                vec![serde_json::to_value(wallet_account)?],
+            )
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+
+    /// Returns `None` for wallet accounts not owned by an Aptos wallet
+    pub async fn get_owner_address(&self, wallet_id: WalletId) -> Result<Option<AccountAddress>> {
+        Ok(self.get_wallet_account(wallet_id).await?.wallet_address)
+    }
+
+    pub async fn get_fee_config(&self, wallet_id: WalletId) -> Result<FeeConfig> {
+        Ok(self.get_wallet_account(wallet_id).await?.fee_config)
+    }
+
+    pub async fn get_wallet_account_asset(
+        &self,
+        wallet_id: WalletId,
+        asset: AccountAddress,
+    ) -> Result<AccountAsset> {
+        let function = "get_wallet_account_asset";
+        let data = self
+            .client
+            .view(
+                MODULE_NAME,
+                function,
+                vec![
+                    bytes_arg(wallet_id.as_bytes()),
+                    serde_json::to_value(asset)?,
+                ],
+            )
+            .await?;
+        expect_len(function, &data, 1)?;
+
+        Ok(serde_json::from_value(data[0].clone())?)
+    }
+
+    pub async fn get_wallet_account_assets(
+        &self,
+        wallet_id: WalletId,
+    ) -> Result<OrderedMap<AccountAddress, AccountAsset>> {
+        let function = "get_wallet_account_assets";
+        let data = self
+            .client
+            .view(MODULE_NAME, function, vec![bytes_arg(wallet_id.as_bytes())])
+            .await?;
+        expect_len(function, &data, 2)?;
+
+        let assets = serde_json::from_value::<Vec<AccountAddress>>(data[0].clone())?;
+        let values = serde_json::from_value::<Vec<AccountAsset>>(data[1].clone())?;
+
+        Ok(OrderedMap {
+            entries: assets
+                .into_iter()
+                .zip(values)
+                .map(|(key, value)| OrderedMapEntry { key, value })
+                .collect(),
+        })
+    }
+
+    /// Walks up to `max` levels of the referral chain, same as `wallet_account::get_referrers`
+    pub async fn get_referrers(&self, wallet_id: WalletId, max: u8) -> Result<Vec<Referrer>> {
+        let storage_address = self.storage_address().await?;
+        let mut referrers = vec![];
+        let mut account = self.get_wallet_account(wallet_id).await?;
+
+        for level in 0..max as usize {
+            let Some(referrer_wallet_id) = account.referrer_wallet_id else {
+                break;
+            };
+
+            let address = wallet_account_address(storage_address, referrer_wallet_id);
+            let referrer = match self.get_wallet_account_at(address).await {
+                Ok(referrer) => Some(referrer),
+                Err(Error::NotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+            referrers.push(Referrer {
+                wallet_account: address,
+                share_percent: referrer
+                    .as_ref()
+                    .and_then(|referrer| referrer.fee_config.referral_percents.get(level).copied()),
+            });
+
+            match referrer {
+                Some(referrer) => account = referrer,
+                None => break,
+            }
+        }
+
+        Ok(referrers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wallet_account_address() {
+        let storage_address = AccountAddress::from_hex_literal("0xabc").unwrap();
+        let wallet_id = WalletId([7u8; 32]);
+
+        let mut seed = vec![2, WALLET_ACCOUNT_SEED.len() as u8];
+        seed.extend_from_slice(WALLET_ACCOUNT_SEED);
+        seed.push(32);
+        seed.extend_from_slice(wallet_id.as_bytes());
+
+        assert_eq!(
+            wallet_account_address(storage_address, wallet_id),
+            create_object_address(storage_address, &seed)
+        );
+        assert_ne!(
+            wallet_account_address(storage_address, wallet_id),
+            wallet_account_address(storage_address, WalletId([8u8; 32]))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_wallet_account() {
+        let account = serde_json::from_value::<WalletAccount>(serde_json::json!({
+            "wallet_id": format!("0x{}", "01".repeat(32)),
+            "chain_id": 0,
+            "wallet_address": { "vec": ["0x1"] },
+            "referrer_wallet_id": "0x",
+            "assets": {
+                "entries": [{
+                    "key": "0xa",
+                    "value": {
+                        "current_amount": "100",
+                        "deposited_amount": "150",
+                        "lp_amount": "100",
+                        "swap_out_amount": "0",
+                        "swap_in_amount": "0",
+                        "distributed_amount": "0",
+                        "withdrawn_amount": "50",
+                        "interest_amount": "10",
+                        "interest_share_amount": "2",
+                        "rewards": { "entries": [] }
+                    }
+                }]
+            },
+            "system_fee_percent": { "vec": [] },
+            "referral_percents": ["100"],
+            "extend_ref": { "self": "0x2" }
+        }))
+        .unwrap();
+
+        assert_eq!(account.wallet_id, WalletId([1u8; 32]));
+        assert_eq!(account.wallet_address, Some(AccountAddress::ONE));
+        assert_eq!(account.referrer_wallet_id, None);
+        assert_eq!(account.fee_config.system_fee_percent, None);
+        assert_eq!(account.fee_config.referral_percents, vec![100]);
+
+        let asset = account
+            .assets
+            .get(&AccountAddress::from_hex_literal("0xa").unwrap())
+            .unwrap();
+        assert_eq!(asset.withdrawn_amount, 50);
+        assert_eq!(asset.net_interest_amount(), 8);
+    }
+}
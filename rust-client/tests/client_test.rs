@@ -3,7 +3,7 @@ use std::str::FromStr;
 use anyhow::Result;
 use aptos_sdk::move_types::account_address::AccountAddress;
 use aptos_sdk::types::chain_id::NamedChain;
-use moneyfi_client::Client;
+use moneyfi_client::{Client, WalletId};
 
 #[tokio::test]
 async fn basic_usage() -> Result<()> {
@@ -11,7 +11,7 @@ async fn basic_usage() -> Result<()> {
     let mut client = Client::new(
         NamedChain::MAINNET.to_string(),
         "0x97c9ffc7143c5585090f9ade67d19ac95f3b3e7008ed86c73c947637e2862f56".to_string(),
-    );
+    )?;
 
     let usdc = AccountAddress::from_str(
         "0xbae207659db88bea0cbead6da0ed00aac12edcdda169e591cd41c94180b46f3b",
@@ -21,16 +21,18 @@ async fn basic_usage() -> Result<()> {
     dbg!(fee_data);
 
     //send tx
+    client.connect("0x1234".to_string())?;
+    // strategies share the signer connected at creation time
     let mut strategy = client.strategy_aries();
     strategy.with_vault_name("USDTVault".to_string());
 
     client
-        .connect("0x1234".to_string())
         .deposit_to_strategy(
-            "wallet_1".into(),
+            WalletId::from_address(AccountAddress::from_str("0x1234")?),
             strategy,
             AccountAddress::from_str("0xa")?,
             1000,
+            None,
         )
         .await?;
 
@@ -0,0 +1,168 @@
+//! Per-call transaction settings shared by the MoneyFi and Aries clients
+
+use std::fmt;
+
+use aptos_sdk::transaction_builder::TransactionBuilder;
+
+/// How the gas unit price of a transaction is chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GasUnitPrice {
+    Fixed(u64),
+    /// The node's current gas estimate
+    Estimate,
+    /// The node's current gas estimate scaled by a multiplier
+    EstimateTimes(f64),
+}
+
+/// Per-call transaction settings. Unset fields fall back to the client defaults.
+#[derive(Debug, Clone, Default)]
+pub struct TxOptions {
+    pub max_gas_amount: Option<u64>,
+    pub gas_unit_price: Option<GasUnitPrice>,
+    pub expiration_timestamp_secs: Option<u64>,
+    /// Simulate first and set max gas to the gas used times this margin, at least 1.0
+    pub simulate_gas_margin: Option<f64>,
+}
+
+/// Rejected `TxOptions` value
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxOptionsError(String);
+
+impl fmt::Display for TxOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for TxOptionsError {}
+
+impl TxOptions {
+    pub fn max_gas_amount(mut self, max_gas_amount: u64) -> Self {
+        self.max_gas_amount = Some(max_gas_amount);
+        self
+    }
+
+    pub fn gas_unit_price(mut self, gas_unit_price: GasUnitPrice) -> Self {
+        self.gas_unit_price = Some(gas_unit_price);
+        self
+    }
+
+    pub fn expiration_timestamp_secs(mut self, expiration_timestamp_secs: u64) -> Self {
+        self.expiration_timestamp_secs = Some(expiration_timestamp_secs);
+        self
+    }
+
+    pub fn simulate_gas_margin(mut self, margin: f64) -> Self {
+        self.simulate_gas_margin = Some(margin);
+        self
+    }
+
+    /// Rejects multipliers and margins that would underprice the transaction or run it
+    /// out of gas
+    pub fn validate(&self) -> Result<(), TxOptionsError> {
+        match self.gas_unit_price {
+            Some(GasUnitPrice::EstimateTimes(multiplier))
+                if !multiplier.is_finite() || multiplier <= 0.0 =>
+            {
+                return Err(TxOptionsError(format!(
+                    "gas unit price multiplier must be positive, got {}",
+                    multiplier
+                )));
+            }
+            _ => {}
+        }
+        match self.simulate_gas_margin {
+            Some(margin) if !margin.is_finite() || margin < 1.0 => {
+                return Err(TxOptionsError(format!(
+                    "simulate gas margin must be at least 1.0, got {}",
+                    margin
+                )));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Whether `fix_gas_unit_price` needs the node's gas estimate
+    pub fn needs_gas_estimate(&self) -> bool {
+        matches!(
+            self.gas_unit_price,
+            Some(GasUnitPrice::Estimate | GasUnitPrice::EstimateTimes(_))
+        )
+    }
+
+    /// Turns an estimate based gas unit price into a fixed one
+    pub fn fix_gas_unit_price(&mut self, estimate: u64) {
+        let price = match self.gas_unit_price {
+            Some(GasUnitPrice::Estimate) => estimate,
+            Some(GasUnitPrice::EstimateTimes(multiplier)) => {
+                (estimate as f64 * multiplier).ceil() as u64
+            }
+            _ => return,
+        };
+
+        self.gas_unit_price = Some(GasUnitPrice::Fixed(price));
+    }
+
+    /// Max gas amount for a simulation that used `gas_used`
+    pub fn max_gas_with_margin(gas_used: u64, margin: f64) -> u64 {
+        (gas_used as f64 * margin).ceil() as u64
+    }
+
+    /// Sets the fixed values on the builder, estimate based prices must be fixed first
+    pub fn apply(&self, mut builder: TransactionBuilder) -> TransactionBuilder {
+        if let Some(max_gas_amount) = self.max_gas_amount {
+            builder = builder.max_gas_amount(max_gas_amount);
+        }
+        if let Some(GasUnitPrice::Fixed(price)) = self.gas_unit_price {
+            builder = builder.gas_unit_price(price);
+        }
+        if let Some(expiration) = self.expiration_timestamp_secs {
+            builder = builder.expiration_timestamp_secs(expiration);
+        }
+
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(TxOptions::default().validate().is_ok());
+        assert!(
+            TxOptions::default()
+                .gas_unit_price(GasUnitPrice::EstimateTimes(1.5))
+                .simulate_gas_margin(1.0)
+                .validate()
+                .is_ok()
+        );
+
+        for margin in [0.9, -1.0, f64::NAN, f64::INFINITY] {
+            let opts = TxOptions::default().simulate_gas_margin(margin);
+            assert!(opts.validate().is_err(), "margin {}", margin);
+        }
+        for multiplier in [0.0, -2.0, f64::NAN] {
+            let opts = TxOptions::default().gas_unit_price(GasUnitPrice::EstimateTimes(multiplier));
+            assert!(opts.validate().is_err(), "multiplier {}", multiplier);
+        }
+    }
+
+    #[test]
+    fn test_fix_gas_unit_price() {
+        let mut opts = TxOptions::default().gas_unit_price(GasUnitPrice::EstimateTimes(1.5));
+        assert!(opts.needs_gas_estimate());
+        opts.fix_gas_unit_price(101);
+        assert_eq!(opts.gas_unit_price, Some(GasUnitPrice::Fixed(152)));
+        assert!(!opts.needs_gas_estimate());
+
+        let mut opts = TxOptions::default();
+        opts.fix_gas_unit_price(101);
+        assert_eq!(opts.gas_unit_price, None);
+
+        assert_eq!(TxOptions::max_gas_with_margin(1000, 1.5), 1500);
+    }
+}